@@ -0,0 +1,360 @@
+use std::error::Error;
+
+use serde_json::json;
+use zbus::{blocking::Connection, proxy};
+
+use crate::dbus::NotificationInfo;
+
+static USAGE: &str = "Usage: rustyfications [COMMAND] [--json]
+
+Commands:
+  daemon                   run notification daemon (default)
+  list                     list notifications that are currently on screen
+  history [--category NAME]
+                           list stored notifications, optionally by category or its class
+  dismiss [--all|ID]       dismiss notification by id or all of them
+  invoke ID KEY            invoke notification action
+  dnd [on|off|toggle]      switch do-not-disturb mode
+  fullscreen on|off [OUTPUT]
+                           report fullscreen client on output (e.g. DP-1) or on popups one
+  reload                   reload configuration
+  center                   show or hide notification center
+  help                     print this message
+
+Options:
+  --json                   machine readable output";
+
+#[proxy(
+    interface = "com.bzglve.rustyfications.Control",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications",
+    gen_async = false
+)]
+trait Control {
+    fn list(&self) -> zbus::Result<Vec<NotificationInfo>>;
+    fn history(&self) -> zbus::Result<Vec<NotificationInfo>>;
+    fn history_by_category(&self, category: &str) -> zbus::Result<Vec<NotificationInfo>>;
+    fn dismiss(&self, id: u32) -> zbus::Result<bool>;
+    fn dismiss_all(&self) -> zbus::Result<u32>;
+    fn invoke_action(&self, id: u32, key: &str) -> zbus::Result<bool>;
+    fn set_dnd(&self, enabled: bool) -> zbus::Result<bool>;
+    fn toggle_dnd(&self) -> zbus::Result<bool>;
+    fn set_fullscreen(&self, output: &str, active: bool) -> zbus::Result<()>;
+    fn reload(&self) -> zbus::Result<()>;
+    fn toggle_center(&self) -> zbus::Result<()>;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Daemon,
+    List,
+    /// Optionally filtered by category
+    History(Option<String>),
+    /// `None` to dismiss all
+    Dismiss(Option<u32>),
+    Invoke(u32, String),
+    /// `None` to toggle
+    Dnd(Option<bool>),
+    /// Optionally on given output
+    Fullscreen(bool, Option<String>),
+    Reload,
+    Center,
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub json: bool,
+}
+
+impl Args {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut json = false;
+        let args: Vec<String> = args
+            .filter(|arg| {
+                if arg == "--json" {
+                    json = true;
+                    false
+                } else {
+                    true
+                }
+            })
+            .collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let parse_id = |id: &str| {
+            id.parse::<u32>()
+                .map_err(|_| format!("Invalid notification id: {}", id))
+        };
+
+        let command = match args.as_slice() {
+            [] | ["daemon"] => Command::Daemon,
+            ["list"] => Command::List,
+            ["history"] => Command::History(None),
+            ["history", "--category", category] => Command::History(Some(category.to_string())),
+            ["dismiss", "--all"] => Command::Dismiss(None),
+            ["dismiss", id] => Command::Dismiss(Some(parse_id(id)?)),
+            ["invoke", id, key] => Command::Invoke(parse_id(id)?, key.to_string()),
+            ["dnd"] | ["dnd", "toggle"] => Command::Dnd(None),
+            ["dnd", "on"] => Command::Dnd(Some(true)),
+            ["dnd", "off"] => Command::Dnd(Some(false)),
+            ["fullscreen", "on"] => Command::Fullscreen(true, None),
+            ["fullscreen", "off"] => Command::Fullscreen(false, None),
+            ["fullscreen", "on", output] => Command::Fullscreen(true, Some(output.to_string())),
+            ["fullscreen", "off", output] => Command::Fullscreen(false, Some(output.to_string())),
+            ["reload"] => Command::Reload,
+            ["center"] => Command::Center,
+            ["help"] | ["--help"] | ["-h"] => Command::Help,
+            _ => {
+                return Err(format!(
+                    "Unknown arguments: {}\n\n{}",
+                    args.join(" "),
+                    USAGE
+                ))
+            }
+        };
+
+        Ok(Self { command, json })
+    }
+}
+
+/// Runs client command against already running daemon
+pub fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    if args.command == Command::Help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let connection = Connection::session()?;
+    let proxy = ControlProxy::new(&connection)?;
+
+    match args.command {
+        Command::Daemon | Command::Help => unreachable!(),
+        Command::List => print_notifications(&proxy.list().map_err(map_err)?, args.json)?,
+        Command::History(category) => {
+            let list = match category {
+                Some(category) => proxy.history_by_category(&category),
+                None => proxy.history(),
+            }
+            .map_err(map_err)?;
+            print_notifications(&list, args.json)?
+        }
+        Command::Dismiss(None) => {
+            let count = proxy.dismiss_all().map_err(map_err)?;
+            print_value(
+                count,
+                &format!("Dismissed {} notification(s)", count),
+                args.json,
+            )?;
+        }
+        Command::Dismiss(Some(id)) => {
+            if !proxy.dismiss(id).map_err(map_err)? {
+                return Err(format!("No notification with id: {}", id).into());
+            }
+            print_value(
+                json!({ "dismissed": id }),
+                &format!("Dismissed notification {}", id),
+                args.json,
+            )?;
+        }
+        Command::Invoke(id, key) => {
+            if !proxy.invoke_action(id, &key).map_err(map_err)? {
+                return Err(format!("No action '{}' for notification with id: {}", key, id).into());
+            }
+            print_value(
+                json!({ "invoked": key, "id": id }),
+                &format!("Invoked action '{}' of notification {}", key, id),
+                args.json,
+            )?;
+        }
+        Command::Dnd(value) => {
+            let state = match value {
+                Some(enabled) => proxy.set_dnd(enabled),
+                None => proxy.toggle_dnd(),
+            }
+            .map_err(map_err)?;
+            print_value(
+                state,
+                &format!("Do-not-disturb: {}", if state { "on" } else { "off" }),
+                args.json,
+            )?;
+        }
+        Command::Fullscreen(active, output) => {
+            let output = output.unwrap_or_default();
+            proxy.set_fullscreen(&output, active).map_err(map_err)?;
+            print_value(
+                json!({ "fullscreen": active, "output": output }),
+                &format!(
+                    "Fullscreen{}: {}",
+                    if output.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" on {}", output)
+                    },
+                    if active { "on" } else { "off" }
+                ),
+                args.json,
+            )?;
+        }
+        Command::Reload => {
+            proxy.reload().map_err(map_err)?;
+            print_value(
+                json!({ "reloaded": true }),
+                "Configuration reloaded",
+                args.json,
+            )?;
+        }
+        Command::Center => {
+            proxy.toggle_center().map_err(map_err)?;
+            print_value(
+                json!({ "center": "toggled" }),
+                "Notification center toggled",
+                args.json,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn map_err(e: zbus::Error) -> Box<dyn Error> {
+    match e {
+        zbus::Error::MethodError(name, _, _)
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            "Daemon is not running".into()
+        }
+        zbus::Error::MethodError(_, Some(message), _) => message.into(),
+        e => e.into(),
+    }
+}
+
+fn print_value(value: impl serde::Serialize, text: &str, json: bool) -> serde_json::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(&value)?);
+    } else {
+        println!("{}", text);
+    }
+    Ok(())
+}
+
+fn print_notifications(list: &[NotificationInfo], json: bool) -> serde_json::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(list)?);
+        return Ok(());
+    }
+
+    for n in list {
+        let urgency = match n.urgency {
+            0 => "low",
+            2 => "critical",
+            _ => "normal",
+        };
+        println!("[{}] {} ({}): {}", n.id, n.app_name, urgency, n.summary);
+        if !n.body.is_empty() {
+            for line in n.body.lines() {
+                println!("    {}", line);
+            }
+        }
+        if !n.actions.is_empty() {
+            let keys: Vec<&str> = n.actions.iter().map(|(key, _)| key.as_str()).collect();
+            println!("    actions: {}", keys.join(", "));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    fn command(args: &str) -> Command {
+        parse(args).unwrap().command
+    }
+
+    #[test]
+    fn daemon_is_default() {
+        assert_eq!(command(""), Command::Daemon);
+        assert_eq!(command("daemon"), Command::Daemon);
+    }
+
+    #[test]
+    fn json_flag_anywhere() {
+        assert!(!parse("list").unwrap().json);
+        assert!(parse("--json list").unwrap().json);
+        assert!(parse("list --json").unwrap().json);
+        assert!(parse("history --json --category email").unwrap().json);
+        assert_eq!(
+            command("history --json --category email"),
+            Command::History(Some("email".to_owned()))
+        );
+    }
+
+    #[test]
+    fn history() {
+        assert_eq!(command("history"), Command::History(None));
+        assert_eq!(
+            command("history --category email.arrived"),
+            Command::History(Some("email.arrived".to_owned()))
+        );
+        assert!(parse("history --category").is_err());
+    }
+
+    #[test]
+    fn dismiss_and_invoke() {
+        assert_eq!(command("dismiss --all"), Command::Dismiss(None));
+        assert_eq!(command("dismiss 42"), Command::Dismiss(Some(42)));
+        assert_eq!(
+            command("invoke 42 default"),
+            Command::Invoke(42, "default".to_owned())
+        );
+        assert!(parse("dismiss").is_err());
+        assert!(parse("invoke 42").is_err());
+    }
+
+    #[test]
+    fn bad_id() {
+        for args in ["dismiss abc", "dismiss -1", "invoke 4294967296 default"] {
+            let error = parse(args).unwrap_err();
+            assert!(error.starts_with("Invalid notification id"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn dnd() {
+        assert_eq!(command("dnd"), Command::Dnd(None));
+        assert_eq!(command("dnd toggle"), Command::Dnd(None));
+        assert_eq!(command("dnd on"), Command::Dnd(Some(true)));
+        assert_eq!(command("dnd off"), Command::Dnd(Some(false)));
+        assert!(parse("dnd maybe").is_err());
+    }
+
+    #[test]
+    fn fullscreen() {
+        assert_eq!(command("fullscreen on"), Command::Fullscreen(true, None));
+        assert_eq!(command("fullscreen off"), Command::Fullscreen(false, None));
+        assert_eq!(
+            command("fullscreen on DP-1"),
+            Command::Fullscreen(true, Some("DP-1".to_owned()))
+        );
+        assert_eq!(
+            command("fullscreen off DP-1 --json"),
+            Command::Fullscreen(false, Some("DP-1".to_owned()))
+        );
+        assert!(parse("fullscreen").is_err());
+        assert!(parse("fullscreen DP-1").is_err());
+    }
+
+    #[test]
+    fn unknown_arguments() {
+        let error = parse("frobnicate now").unwrap_err();
+        assert!(error.starts_with("Unknown arguments: frobnicate now"));
+        assert!(error.contains(USAGE));
+        assert_eq!(command("-h"), Command::Help);
+    }
+}
@@ -1,32 +1,38 @@
 use std::{
     collections::HashMap,
     fs,
+    path::PathBuf,
+    rc::Rc,
     sync::{LazyLock, Mutex},
+    time::Duration,
 };
 
-use gtk::glib;
-use log::LevelFilter as LogLevelFilter;
+use gtk::{gio, glib};
+use log::{error, info, LevelFilter as LogLevelFilter};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    dbus::{Category, Hints, Urgency},
+    utils::watch_file,
+};
+
+pub mod quiet_hours;
+pub mod rules;
+
 mod level_filter {
     use super::*;
 
-    #[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+    #[derive(Debug, Default, Deserialize, Serialize, Clone, Copy)]
     pub enum LevelFilter {
         Off,
         Error,
         Warn,
+        #[default]
         Info,
         Debug,
         Trace,
     }
 
-    impl Default for LevelFilter {
-        fn default() -> Self {
-            Self::Info
-        }
-    }
-
     impl From<LevelFilter> for LogLevelFilter {
         fn from(value: LevelFilter) -> Self {
             match value {
@@ -91,18 +97,697 @@ pub mod edge {
     }
 }
 
-mod defaults {
+pub mod history {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct History {
+        #[serde(default = "defaults::enabled")]
+        pub enabled: bool,
+        /// Maximum number of stored notifications
+        #[serde(default = "defaults::max_entries")]
+        pub max_entries: usize,
+        /// Maximum age of stored notifications in seconds
+        #[serde(default = "defaults::max_age")]
+        pub max_age: u64,
+    }
+
+    impl Default for History {
+        fn default() -> Self {
+            Self {
+                enabled: defaults::enabled(),
+                max_entries: defaults::max_entries(),
+                max_age: defaults::max_age(),
+            }
+        }
+    }
+
+    mod defaults {
+        pub fn enabled() -> bool {
+            true
+        }
+
+        pub fn max_entries() -> usize {
+            100
+        }
+
+        pub fn max_age() -> u64 {
+            // a week
+            7 * 24 * 60 * 60
+        }
+    }
+}
+
+pub mod dnd {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Dnd {
+        /// Show critical notifications anyway
+        #[serde(default = "defaults::critical_bypass")]
+        pub critical_bypass: bool,
+        /// Show popup with number of missed notifications when do-not-disturb ends
+        #[serde(default = "defaults::summary")]
+        pub summary: bool,
+        /// Keep do-not-disturb state across restarts
+        #[serde(default = "defaults::persist")]
+        pub persist: bool,
+    }
+
+    impl Default for Dnd {
+        fn default() -> Self {
+            Self {
+                critical_bypass: defaults::critical_bypass(),
+                summary: defaults::summary(),
+                persist: defaults::persist(),
+            }
+        }
+    }
+
+    mod defaults {
+        pub fn critical_bypass() -> bool {
+            true
+        }
+
+        pub fn summary() -> bool {
+            true
+        }
+
+        pub fn persist() -> bool {
+            false
+        }
+    }
+}
+
+pub mod hooks {
+    use serde::{Deserialize, Serialize};
+
+    /// Commands spawned on notification lifecycle events.
+    /// Notification is passed as JSON on stdin and as `RUSTYFICATIONS_*` environment variables
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Hooks {
+        /// New notification arrived (even if it is suppressed by do-not-disturb)
+        #[serde(default)]
+        pub on_notify: Option<String>,
+        /// Notification was replaced by the client
+        #[serde(default)]
+        pub on_replace: Option<String>,
+        /// Notification was closed. Reason is passed too
+        #[serde(default)]
+        pub on_close: Option<String>,
+        /// Action was invoked. Action key is passed too
+        #[serde(default)]
+        pub on_action: Option<String>,
+        /// Milliseconds after which hook is killed
+        #[serde(default = "defaults::timeout")]
+        pub timeout: u64,
+    }
+
+    impl Default for Hooks {
+        fn default() -> Self {
+            Self {
+                on_notify: None,
+                on_replace: None,
+                on_close: None,
+                on_action: None,
+                timeout: defaults::timeout(),
+            }
+        }
+    }
+
+    mod defaults {
+        pub fn timeout() -> u64 {
+            10000
+        }
+    }
+}
+
+pub mod grouping {
+    use serde::{Deserialize, Serialize};
+
+    use crate::dbus::Details;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum GroupBy {
+        AppName,
+        /// Falls back to app name if notification has no desktop entry
+        DesktopEntry,
+        /// Only notifications with `group` set by rules are grouped
+        Rule,
+    }
+
+    /// Notifications with the same key are collapsed into one expandable popup
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Grouping {
+        #[serde(default = "defaults::enabled")]
+        pub enabled: bool,
+        /// Key set by rules always takes precedence
+        #[serde(default = "defaults::by")]
+        pub by: GroupBy,
+        /// Number of notifications on screen that forms a group
+        #[serde(default = "defaults::min_size")]
+        pub min_size: usize,
+    }
+
+    impl Grouping {
+        pub fn key(&self, details: &Details) -> Option<String> {
+            if !self.enabled {
+                return None;
+            }
+
+            details.group.clone().or_else(|| match self.by {
+                GroupBy::AppName => details.app_name.clone(),
+                GroupBy::DesktopEntry => details
+                    .hints
+                    .desktop_entry
+                    .clone()
+                    .or_else(|| details.app_name.clone()),
+                GroupBy::Rule => None,
+            })
+        }
+    }
+
+    impl Default for Grouping {
+        fn default() -> Self {
+            Self {
+                enabled: defaults::enabled(),
+                by: defaults::by(),
+                min_size: defaults::min_size(),
+            }
+        }
+    }
+
+    mod defaults {
+        use super::GroupBy;
+
+        pub fn enabled() -> bool {
+            false
+        }
+
+        pub fn by() -> GroupBy {
+            GroupBy::AppName
+        }
+
+        pub fn min_size() -> usize {
+            2
+        }
+    }
+}
+
+pub mod category {
+    use serde::{Deserialize, Serialize};
+
+    /// Used when notification doesn't provide its own
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct CategoryDefaults {
+        /// Icon name or absolute path
+        #[serde(default)]
+        pub icon: Option<String>,
+        /// In milliseconds. 0 means never expire. Not applied to critical notifications
+        #[serde(default)]
+        pub expire_timeout: Option<u64>,
+        /// Sound name from sound theme or absolute path
+        #[serde(default)]
+        pub sound: Option<String>,
+    }
+
+    impl CategoryDefaults {
+        /// Fields of `self` take precedence
+        pub fn or(self, other: Self) -> Self {
+            Self {
+                icon: self.icon.or(other.icon),
+                expire_timeout: self.expire_timeout.or(other.expire_timeout),
+                sound: self.sound.or(other.sound),
+            }
+        }
+    }
+}
+
+pub mod sound {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::dbus::Urgency;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Backend {
+        /// GTK media stream (GStreamer)
+        Media,
+        /// Sound is resolved and logged but not played. Useful for testing
+        Null,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Sound {
+        /// Global mute. Do-not-disturb also mutes sounds
+        #[serde(default = "defaults::mute")]
+        pub mute: bool,
+        /// Freedesktop sound theme used to resolve `sound-name` hint
+        #[serde(default = "defaults::theme")]
+        pub theme: String,
+        /// Sound name from sound theme or absolute path.
+        /// Used if neither notification nor its category provides one
+        #[serde(default = "defaults::urgency")]
+        pub urgency: HashMap<Urgency, String>,
+        #[serde(default = "defaults::backend")]
+        pub backend: Backend,
+    }
+
+    impl Default for Sound {
+        fn default() -> Self {
+            Self {
+                mute: defaults::mute(),
+                theme: defaults::theme(),
+                urgency: defaults::urgency(),
+                backend: defaults::backend(),
+            }
+        }
+    }
+
+    mod defaults {
+        use std::collections::HashMap;
+
+        use super::{Backend, Urgency};
+
+        pub fn mute() -> bool {
+            false
+        }
+
+        pub fn theme() -> String {
+            "freedesktop".to_owned()
+        }
+
+        pub fn urgency() -> HashMap<Urgency, String> {
+            HashMap::new()
+        }
+
+        pub fn backend() -> Backend {
+            Backend::Media
+        }
+    }
+}
+
+pub mod body_images {
+    use serde::{Deserialize, Serialize};
+
+    /// `<img>` tags in notification body
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct BodyImages {
+        /// Alt text is shown instead of images if disabled
+        #[serde(default = "defaults::enabled")]
+        pub enabled: bool,
+        /// Images are scaled down to fit
+        #[serde(default = "defaults::max_width")]
+        pub max_width: i32,
+        #[serde(default = "defaults::max_height")]
+        pub max_height: i32,
+        /// Bigger files are not loaded at all. In bytes
+        #[serde(default = "defaults::max_file_size")]
+        pub max_file_size: u64,
+        /// Images with bigger width or height are not loaded at all. In pixels
+        #[serde(default = "defaults::max_dimension")]
+        pub max_dimension: i32,
+    }
+
+    impl Default for BodyImages {
+        fn default() -> Self {
+            Self {
+                enabled: defaults::enabled(),
+                max_width: defaults::max_width(),
+                max_height: defaults::max_height(),
+                max_file_size: defaults::max_file_size(),
+                max_dimension: defaults::max_dimension(),
+            }
+        }
+    }
+
+    mod defaults {
+        pub fn enabled() -> bool {
+            true
+        }
+
+        pub fn max_width() -> i32 {
+            300
+        }
+
+        pub fn max_height() -> i32 {
+            200
+        }
+
+        pub fn max_file_size() -> u64 {
+            10 * 1024 * 1024
+        }
+
+        pub fn max_dimension() -> i32 {
+            4096
+        }
+    }
+}
+
+pub mod keys {
+    use gtk::gdk;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Binding {
+        Dismiss,
+        DefaultAction,
+        /// Zero-based index of action button
+        Action(usize),
+        NextAction,
+        PrevAction,
+    }
+
+    /// Keyboard shortcuts for hovered popup.
+    /// Keys are written as GTK accelerators, e.g. "Escape" or "<Control>d"
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Keys {
+        #[serde(default = "defaults::dismiss")]
+        pub dismiss: Vec<String>,
+        #[serde(default = "defaults::default_action")]
+        pub default_action: Vec<String>,
+        #[serde(default = "defaults::next_action")]
+        pub next_action: Vec<String>,
+        #[serde(default = "defaults::prev_action")]
+        pub prev_action: Vec<String>,
+        /// 1-9 invoke action buttons
+        #[serde(default = "defaults::numbers")]
+        pub numbers: bool,
+    }
+
+    impl Keys {
+        pub fn binding(&self, key: gdk::Key, state: gdk::ModifierType) -> Option<Binding> {
+            let state = state & gtk::accelerator_get_default_mod_mask();
+            let matches = |accels: &[String]| {
+                accels.iter().any(|accel| {
+                    gtk::accelerator_parse(accel.as_str()).is_some_and(|(accel_key, mods)| {
+                        accel_key.to_lower() == key.to_lower() && mods == state
+                    })
+                })
+            };
+
+            if matches(&self.dismiss) {
+                Some(Binding::Dismiss)
+            } else if matches(&self.default_action) {
+                Some(Binding::DefaultAction)
+            } else if matches(&self.next_action) {
+                Some(Binding::NextAction)
+            } else if matches(&self.prev_action) {
+                Some(Binding::PrevAction)
+            } else if self.numbers && state.is_empty() {
+                key.to_unicode()
+                    .and_then(|c| c.to_digit(10))
+                    .filter(|n| *n > 0)
+                    .map(|n| Binding::Action(n as usize - 1))
+            } else {
+                None
+            }
+        }
+    }
+
+    impl Default for Keys {
+        fn default() -> Self {
+            Self {
+                dismiss: defaults::dismiss(),
+                default_action: defaults::default_action(),
+                next_action: defaults::next_action(),
+                prev_action: defaults::prev_action(),
+                numbers: defaults::numbers(),
+            }
+        }
+    }
+
+    mod defaults {
+        pub fn dismiss() -> Vec<String> {
+            vec!["Escape".to_owned()]
+        }
+
+        pub fn default_action() -> Vec<String> {
+            vec!["Return".to_owned(), "KP_Enter".to_owned()]
+        }
+
+        pub fn next_action() -> Vec<String> {
+            vec!["Tab".to_owned()]
+        }
+
+        // Shift+Tab comes as ISO_Left_Tab
+        pub fn prev_action() -> Vec<String> {
+            vec!["<Shift>ISO_Left_Tab".to_owned()]
+        }
+
+        pub fn numbers() -> bool {
+            true
+        }
+    }
+}
+
+pub mod layer {
+    use gtk_layer_shell::Layer as GtkLayer;
+    use serde::{Deserialize, Serialize};
+
+    /// Ordered from the lowest to the highest
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    pub enum Layer {
+        Background,
+        Bottom,
+        Top,
+        Overlay,
+    }
+
+    impl From<Layer> for GtkLayer {
+        fn from(value: Layer) -> Self {
+            match value {
+                Layer::Background => Self::Background,
+                Layer::Bottom => Self::Bottom,
+                Layer::Top => Self::Top,
+                Layer::Overlay => Self::Overlay,
+            }
+        }
+    }
+
+    /// What to do with popups while there is a fullscreen client on their output.
+    /// Popups on `Overlay` layer are always shown
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Fullscreen {
+        /// Raise popups to `Overlay`
+        Show,
+        /// Unmap popups until fullscreen ends. Their timeouts keep running
+        Hide,
+        /// Hold new notifications until fullscreen ends
+        Queue,
+    }
+}
+
+pub mod output {
+    use gtk::{gdk, prelude::*};
+    use serde::{Deserialize, Serialize};
+
+    /// Where popups are shown
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+    pub enum Output {
+        /// Compositor decides. Usually it is the focused one
+        #[default]
+        Focused,
+        /// Position in the list of monitors
+        Index(u32),
+        /// Connector name like "DP-1"
+        Connector(String),
+    }
+
+    impl Output {
+        /// Returns `None` if compositor should decide or output is not plugged in
+        pub fn monitor(&self) -> Option<gdk::Monitor> {
+            let monitors = gdk::Display::default()?.monitors();
+            let monitor = match self {
+                Self::Focused => return None,
+                Self::Index(index) => monitors.item(*index).and_downcast::<gdk::Monitor>(),
+                Self::Connector(name) => monitors
+                    .iter::<gdk::Monitor>()
+                    .flatten()
+                    .find(|m| m.connector().is_some_and(|c| c == name.as_str())),
+            };
+
+            if monitor.is_none() {
+                log::warn!(
+                    "Output {:?} is not found. Falling back to focused one",
+                    self
+                );
+            }
+            monitor
+        }
+    }
+}
+
+pub mod animation {
     use std::collections::HashMap;
 
+    use gtk::RevealerTransitionType;
+    use serde::{Deserialize, Serialize};
+
+    use super::edge::{Edge, EdgeInfo};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum Kind {
+        None,
+        /// Direction is taken from anchored edge
+        Slide,
+        Fade,
+    }
+
+    /// Popups entry and exit transitions
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Animation {
+        #[serde(default = "defaults::kind")]
+        pub kind: Kind,
+        /// Milliseconds
+        #[serde(default = "defaults::duration")]
+        pub duration: u32,
+    }
+
+    impl Animation {
+        /// Vertical edges are preferred, so closing popup collapses
+        /// and the rest of the stack moves smoothly
+        pub fn transition(&self, edges: &HashMap<Edge, EdgeInfo>) -> RevealerTransitionType {
+            match self.kind {
+                Kind::None => RevealerTransitionType::None,
+                Kind::Fade => RevealerTransitionType::Crossfade,
+                Kind::Slide if edges.contains_key(&Edge::Top) => RevealerTransitionType::SlideDown,
+                Kind::Slide if edges.contains_key(&Edge::Bottom) => RevealerTransitionType::SlideUp,
+                Kind::Slide if edges.contains_key(&Edge::Left) => {
+                    RevealerTransitionType::SlideRight
+                }
+                Kind::Slide if edges.contains_key(&Edge::Right) => {
+                    RevealerTransitionType::SlideLeft
+                }
+                Kind::Slide => RevealerTransitionType::SlideDown,
+            }
+        }
+
+        pub fn duration(&self) -> u32 {
+            match self.kind {
+                Kind::None => 0,
+                _ => self.duration,
+            }
+        }
+    }
+
+    impl Default for Animation {
+        fn default() -> Self {
+            Self {
+                kind: defaults::kind(),
+                duration: defaults::duration(),
+            }
+        }
+    }
+
+    mod defaults {
+        use super::Kind;
+
+        pub fn kind() -> Kind {
+            Kind::Slide
+        }
+
+        pub fn duration() -> u32 {
+            200
+        }
+    }
+}
+
+pub mod center {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::edge::{Edge, EdgeInfo};
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Center {
+        /// Unlike popups opposite edges are allowed here to stretch the panel
+        #[serde(default = "defaults::edges")]
+        pub edges: HashMap<Edge, EdgeInfo>,
+        #[serde(default = "defaults::width")]
+        pub width: i32,
+    }
+
+    impl Default for Center {
+        fn default() -> Self {
+            Self {
+                edges: defaults::edges(),
+                width: defaults::width(),
+            }
+        }
+    }
+
+    mod defaults {
+        use std::collections::HashMap;
+
+        use super::{Edge, EdgeInfo};
+
+        pub fn edges() -> HashMap<Edge, EdgeInfo> {
+            [Edge::Top, Edge::Right, Edge::Bottom]
+                .into_iter()
+                .map(|edge| {
+                    (
+                        edge,
+                        EdgeInfo {
+                            margin: 5,
+                            padding: 0,
+                        },
+                    )
+                })
+                .collect()
+        }
+
+        pub fn width() -> i32 {
+            420
+        }
+    }
+}
+
+mod defaults {
+    use std::{collections::HashMap, path::PathBuf};
+
     use super::{
+        animation::Animation,
+        body_images::BodyImages,
+        category::CategoryDefaults,
+        center::Center,
+        dnd::Dnd,
         edge::{Edge, EdgeInfo},
+        grouping::Grouping,
+        history::History,
+        hooks::Hooks,
+        keys::Keys,
+        layer::{Fullscreen, Layer},
         level_filter::LevelFilter,
+        output::Output,
+        quiet_hours::QuietHours,
+        rules::Rule,
+        sound::Sound,
+        Urgency,
     };
 
     pub fn expire_timeout() -> u64 {
         5000
     }
 
+    pub fn expire_timeout_low() -> Option<u64> {
+        None
+    }
+
+    pub fn max_visible() -> Option<usize> {
+        None
+    }
+
+    pub fn single_surface() -> bool {
+        false
+    }
+
+    pub fn position_hints() -> bool {
+        false
+    }
+
     pub fn new_on_top() -> bool {
         true
     }
@@ -131,6 +816,74 @@ mod defaults {
         HashMap::new()
     }
 
+    pub fn history() -> History {
+        History::default()
+    }
+
+    pub fn center() -> Center {
+        Center::default()
+    }
+
+    pub fn dnd() -> Dnd {
+        Dnd::default()
+    }
+
+    pub fn grouping() -> Grouping {
+        Grouping::default()
+    }
+
+    pub fn hooks() -> Hooks {
+        Hooks::default()
+    }
+
+    pub fn animation() -> Animation {
+        Animation::default()
+    }
+
+    pub fn keys() -> Keys {
+        Keys::default()
+    }
+
+    pub fn categories() -> HashMap<String, CategoryDefaults> {
+        HashMap::new()
+    }
+
+    pub fn sound() -> Sound {
+        Sound::default()
+    }
+
+    pub fn body_images() -> BodyImages {
+        BodyImages::default()
+    }
+
+    pub fn output() -> Output {
+        Output::default()
+    }
+
+    pub fn layer() -> Layer {
+        Layer::Top
+    }
+
+    pub fn urgency_layers() -> HashMap<Urgency, Layer> {
+        HashMap::from([(Urgency::Critical, Layer::Overlay)])
+    }
+
+    pub fn fullscreen() -> Fullscreen {
+        Fullscreen::Show
+    }
+
+    pub fn style() -> Option<PathBuf> {
+        None
+    }
+
+    pub fn quiet_hours() -> Vec<QuietHours> {
+        Vec::new()
+    }
+
+    pub fn rules() -> Vec<Rule> {
+        Vec::new()
+    }
+
     pub fn edges() -> HashMap<Edge, EdgeInfo> {
         let mut val = HashMap::new();
         val.insert(
@@ -155,6 +908,8 @@ mod defaults {
 pub struct Config {
     #[serde(default = "defaults::expire_timeout")]
     pub expire_timeout: u64,
+    #[serde(default = "defaults::expire_timeout_low")]
+    pub expire_timeout_low: Option<u64>,
     #[serde(default = "defaults::icon_size")]
     pub icon_size: i32,
     #[serde(default = "defaults::log_level")]
@@ -167,53 +922,216 @@ pub struct Config {
     pub icons_alias: HashMap<String, String>,
     #[serde(default = "defaults::new_on_top")]
     pub new_on_top: bool,
+    /// Maximum number of popups on screen. Others wait in queue
+    #[serde(default = "defaults::max_visible")]
+    pub max_visible: Option<usize>,
+    /// Show all popups in one layer-shell window.
+    /// Otherwise every popup has its own. Requires restart
+    #[serde(default = "defaults::single_surface")]
+    pub single_surface: bool,
+    /// Notifications with `x` and `y` hints are placed near that point
+    /// in their own window instead of the stack
+    #[serde(default = "defaults::position_hints")]
+    pub position_hints: bool,
+    #[serde(default = "defaults::output")]
+    pub output: output::Output,
+    #[serde(default = "defaults::layer")]
+    pub layer: layer::Layer,
+    /// Overrides `layer` for given urgencies
+    #[serde(default = "defaults::urgency_layers")]
+    pub urgency_layers: HashMap<Urgency, layer::Layer>,
+    #[serde(default = "defaults::fullscreen")]
+    pub fullscreen: layer::Fullscreen,
     #[serde(default = "defaults::window_size")]
     pub window_size: (i32, i32),
     #[serde(default = "defaults::edges")]
     pub edges: HashMap<edge::Edge, edge::EdgeInfo>,
+    #[serde(default = "defaults::history")]
+    pub history: history::History,
+    #[serde(default = "defaults::center")]
+    pub center: center::Center,
+    #[serde(default = "defaults::dnd")]
+    pub dnd: dnd::Dnd,
+    #[serde(default = "defaults::quiet_hours")]
+    pub quiet_hours: Vec<quiet_hours::QuietHours>,
+    /// Evaluated in order for every incoming notification
+    #[serde(default = "defaults::rules")]
+    pub rules: Vec<rules::Rule>,
+    #[serde(default = "defaults::grouping")]
+    pub grouping: grouping::Grouping,
+    #[serde(default = "defaults::hooks")]
+    pub hooks: hooks::Hooks,
+    #[serde(default = "defaults::animation")]
+    pub animation: animation::Animation,
+    #[serde(default = "defaults::keys")]
+    pub keys: keys::Keys,
+    /// Keyed by full category (`email.arrived`) or its class (`email`).
+    /// Full one takes precedence
+    #[serde(default = "defaults::categories")]
+    pub categories: HashMap<String, category::CategoryDefaults>,
+    #[serde(default = "defaults::sound")]
+    pub sound: sound::Sound,
+    #[serde(default = "defaults::body_images")]
+    pub body_images: body_images::BodyImages,
+    /// User stylesheet. `style.css` in config dir is used if not set
+    #[serde(default = "defaults::style")]
+    pub style: Option<PathBuf>,
 }
 
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(&'static str),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "Failed to read config: {}", e),
+            Error::Parse(e) => write!(
+                f,
+                "Failed to parse config at line {}, column {}: {}",
+                e.position.line, e.position.col, e.code
+            ),
+            Error::Invalid(e) => write!(f, "Invalid config: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl Config {
     pub fn new() -> Option<Self> {
         let config_path = Self::find_config_path()?;
         println!("Found config file: {:?}", config_path);
 
-        let config_string = fs::read_to_string(&config_path).ok()?;
-        let config = ron::from_str::<Self>(&config_string);
-        if let Err(e) = config {
-            eprintln!("{}", e);
-            return None;
+        match Self::load_from(&config_path) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
         }
-        let config = config.unwrap();
+    }
 
-        if config.validate() {
-            Some(config)
-        } else {
-            None
+    pub fn load_from(path: &std::path::Path) -> Result<Self, Error> {
+        let config_string = fs::read_to_string(path).map_err(Error::Io)?;
+        let config = ron::from_str::<Self>(&config_string).map_err(Error::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Re-reads config file and replaces current configuration.
+    /// Defaults are used if there is no config file anymore
+    pub fn reload() -> Result<(), Error> {
+        let config = match Self::find_config_path() {
+            Some(path) => Self::load_from(&path)?,
+            None => Self::default(),
+        };
+
+        log::set_max_level(config.log_level.into());
+        *CONFIG.lock().unwrap() = config;
+        Ok(())
+    }
+
+    /// Calls `f` every time config file is changed, created or removed.
+    /// Every possible location is watched, so config that is created later
+    /// or at higher-priority location is picked up
+    pub fn watch(f: impl Fn() + 'static) -> Vec<gio::FileMonitor> {
+        let f = Rc::new(f);
+        Self::config_paths()
+            .iter()
+            .filter_map(|path| {
+                info!("Watching config file: {:?}", path);
+                let f = f.clone();
+                watch_file(path, move || f())
+                    .inspect_err(|e| error!("Failed to watch config file {:?}: {}", path, e))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Timeout for notifications that didn't specify their own expiration.
+    /// Critical ones are never expired as spec recommends
+    pub fn default_expire_timeout(&self, urgency: Urgency) -> Duration {
+        match urgency {
+            Urgency::Low => {
+                Duration::from_millis(self.expire_timeout_low.unwrap_or(self.expire_timeout))
+            }
+            Urgency::Normal => Duration::from_millis(self.expire_timeout),
+            Urgency::Critical => Duration::MAX,
+        }
+    }
+
+    pub fn category_defaults(&self, category: Option<&Category>) -> category::CategoryDefaults {
+        let Some(category) = category else {
+            return Default::default();
+        };
+        let get = |key: &str| self.categories.get(key).cloned().unwrap_or_default();
+
+        get(&category.to_string()).or(get(&category.class))
+    }
+
+    /// Category default takes precedence over urgency one,
+    /// but critical notifications are still never expired
+    pub fn hints_expire_timeout(&self, hints: &Hints) -> Duration {
+        if hints.urgency == Urgency::Critical {
+            return Duration::MAX;
+        }
+
+        match self
+            .category_defaults(hints.category.as_ref())
+            .expire_timeout
+        {
+            Some(0) => Duration::MAX,
+            Some(timeout) => Duration::from_millis(timeout),
+            None => self.default_expire_timeout(hints.urgency),
         }
     }
 
-    fn find_config_path() -> Option<std::path::PathBuf> {
-        let user_config = glib::user_config_dir().join("rustyfications/config.ron");
-        if user_config.exists() {
-            return Some(user_config);
+    /// Layer for popup with given urgency, taking fullscreen policy into account.
+    /// Other policies than `Show` don't touch the layer
+    pub fn layer(&self, urgency: Urgency, fullscreen: bool) -> layer::Layer {
+        use layer::{Fullscreen, Layer};
+
+        if fullscreen && self.fullscreen == Fullscreen::Show {
+            return Layer::Overlay;
         }
+        self.urgency_layers
+            .get(&urgency)
+            .copied()
+            .unwrap_or(self.layer)
+    }
+
+    /// Path of user stylesheet. It may not exist
+    pub fn style_path(&self) -> PathBuf {
+        self.style
+            .clone()
+            .unwrap_or_else(|| glib::user_config_dir().join("rustyfications/style.css"))
+    }
+
+    fn find_config_path() -> Option<PathBuf> {
+        Self::config_paths().into_iter().find(|path| path.exists())
+    }
 
-        glib::system_config_dirs().iter().find_map(|dir| {
-            let system_config = dir.join("rustyfications/config.ron");
-            system_config.exists().then_some(system_config)
-        })
+    /// User config first, then system ones
+    fn config_paths() -> Vec<PathBuf> {
+        [glib::user_config_dir()]
+            .into_iter()
+            .chain(glib::system_config_dirs())
+            .map(|dir| dir.join("rustyfications/config.ron"))
+            .collect()
     }
 
-    fn validate(&self) -> bool {
+    fn validate(&self) -> Result<(), Error> {
         if self.edges.contains_key(&edge::Edge::Left) && self.edges.contains_key(&edge::Edge::Right)
             || self.edges.contains_key(&edge::Edge::Top)
                 && self.edges.contains_key(&edge::Edge::Bottom)
         {
-            eprintln!("Using two opposite edges is not allowed");
-            false
+            Err(Error::Invalid("Using two opposite edges is not allowed"))
         } else {
-            true
+            Ok(())
         }
     }
 }
@@ -222,7 +1140,15 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             expire_timeout: defaults::expire_timeout(),
+            expire_timeout_low: defaults::expire_timeout_low(),
             new_on_top: defaults::new_on_top(),
+            max_visible: defaults::max_visible(),
+            single_surface: defaults::single_surface(),
+            position_hints: defaults::position_hints(),
+            output: defaults::output(),
+            layer: defaults::layer(),
+            urgency_layers: defaults::urgency_layers(),
+            fullscreen: defaults::fullscreen(),
             icon_size: defaults::icon_size(),
             log_level: defaults::log_level(),
             window_close_icon: defaults::window_close_icon(),
@@ -230,6 +1156,19 @@ impl Default for Config {
             window_size: defaults::window_size(),
             icons_alias: defaults::icon_redefines(),
             edges: defaults::edges(),
+            history: defaults::history(),
+            center: defaults::center(),
+            dnd: defaults::dnd(),
+            quiet_hours: defaults::quiet_hours(),
+            rules: defaults::rules(),
+            grouping: defaults::grouping(),
+            hooks: defaults::hooks(),
+            animation: defaults::animation(),
+            keys: defaults::keys(),
+            categories: defaults::categories(),
+            sound: defaults::sound(),
+            body_images: defaults::body_images(),
+            style: defaults::style(),
         }
     }
 }
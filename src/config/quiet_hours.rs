@@ -0,0 +1,210 @@
+use gtk::glib;
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, UtcOffset, Weekday as TimeWeekday};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl From<TimeWeekday> for Weekday {
+    fn from(value: TimeWeekday) -> Self {
+        match value {
+            TimeWeekday::Monday => Self::Mon,
+            TimeWeekday::Tuesday => Self::Tue,
+            TimeWeekday::Wednesday => Self::Wed,
+            TimeWeekday::Thursday => Self::Thu,
+            TimeWeekday::Friday => Self::Fri,
+            TimeWeekday::Saturday => Self::Sat,
+            TimeWeekday::Sunday => Self::Sun,
+        }
+    }
+}
+
+/// Time of day in `HH:MM` format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ClockTime {
+    hour: u8,
+    minute: u8,
+}
+
+impl ClockTime {
+    fn minutes(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+}
+
+impl TryFrom<String> for ClockTime {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let error = || format!("Expected time in HH:MM format, got \"{}\"", value);
+
+        let (hour, minute) = value.split_once(':').ok_or_else(error)?;
+        let hour: u8 = hour.parse().map_err(|_| error())?;
+        let minute: u8 = minute.parse().map_err(|_| error())?;
+        if hour > 23 || minute > 59 {
+            return Err(error());
+        }
+
+        Ok(Self { hour, minute })
+    }
+}
+
+impl From<ClockTime> for String {
+    fn from(value: ClockTime) -> Self {
+        format!("{:02}:{:02}", value.hour, value.minute)
+    }
+}
+
+/// Period of time when popups are suppressed as in do-not-disturb mode.
+/// If `end` is before `start` period lasts until the next day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Days when period starts. Empty means every day
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: ClockTime,
+    pub end: ClockTime,
+}
+
+impl QuietHours {
+    fn starts_on(&self, weekday: TimeWeekday) -> bool {
+        self.days.is_empty() || self.days.contains(&weekday.into())
+    }
+
+    pub fn is_active(&self, now: OffsetDateTime) -> bool {
+        let minutes = now.hour() as u16 * 60 + now.minute() as u16;
+        let weekday = now.weekday();
+        let (start, end) = (self.start.minutes(), self.end.minutes());
+
+        if start <= end {
+            self.starts_on(weekday) && start <= minutes && minutes < end
+        } else {
+            (self.starts_on(weekday) && minutes >= start)
+                || (self.starts_on(weekday.previous()) && minutes < end)
+        }
+    }
+}
+
+/// Current local time.
+///
+/// `time` refuses to determine local offset when there are other threads running,
+/// so glib is asked for it in that case
+pub fn local_now() -> OffsetDateTime {
+    let offset = UtcOffset::current_local_offset().unwrap_or_else(|_| {
+        glib::DateTime::now_local()
+            .ok()
+            .and_then(|dt| UtcOffset::from_whole_seconds(dt.utc_offset().as_seconds() as i32).ok())
+            .unwrap_or(UtcOffset::UTC)
+    });
+
+    OffsetDateTime::now_utc().to_offset(offset)
+}
+
+pub fn any_active(quiet_hours: &[QuietHours]) -> bool {
+    let now = local_now();
+    quiet_hours.iter().any(|q| q.is_active(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::{Date, Month, Time};
+
+    use super::*;
+
+    /// January 2024 starts on Monday
+    fn at(day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2024, Month::January, day)
+            .unwrap()
+            .with_time(Time::from_hms(hour, minute, 0).unwrap())
+            .assume_utc()
+    }
+
+    fn quiet_hours(days: &[Weekday], start: &str, end: &str) -> QuietHours {
+        QuietHours {
+            days: days.to_vec(),
+            start: start.to_owned().try_into().unwrap(),
+            end: end.to_owned().try_into().unwrap(),
+        }
+    }
+
+    #[test]
+    fn clock_time_format() {
+        let parse = |s: &str| ClockTime::try_from(s.to_owned());
+        assert_eq!(parse("7:05").map(String::from).as_deref(), Ok("07:05"));
+        assert_eq!(parse("23:59").map(String::from).as_deref(), Ok("23:59"));
+        assert!(parse("24:00").is_err());
+        assert!(parse("12:60").is_err());
+        assert!(parse("12").is_err());
+        assert!(parse("ab:cd").is_err());
+    }
+
+    #[test]
+    fn window_within_day() {
+        let lunch = quiet_hours(&[], "12:00", "13:00");
+        assert!(!lunch.is_active(at(1, 11, 59)));
+        assert!(lunch.is_active(at(1, 12, 0)));
+        assert!(lunch.is_active(at(1, 12, 59)));
+        assert!(!lunch.is_active(at(1, 13, 0)));
+    }
+
+    #[test]
+    fn window_across_midnight() {
+        let night = quiet_hours(&[], "22:00", "07:00");
+        assert!(!night.is_active(at(1, 21, 59)));
+        assert!(night.is_active(at(1, 22, 0)));
+        assert!(night.is_active(at(1, 23, 59)));
+        assert!(night.is_active(at(2, 0, 0)));
+        assert!(night.is_active(at(2, 6, 59)));
+        assert!(!night.is_active(at(2, 7, 0)));
+        assert!(!night.is_active(at(2, 12, 0)));
+    }
+
+    #[test]
+    fn window_across_midnight_belongs_to_start_day() {
+        let friday_night = quiet_hours(&[Weekday::Fri], "22:00", "07:00");
+        // Friday
+        assert!(friday_night.is_active(at(5, 23, 0)));
+        // Saturday morning still belongs to Friday
+        assert!(friday_night.is_active(at(6, 6, 0)));
+        // but Saturday night doesn't
+        assert!(!friday_night.is_active(at(6, 23, 0)));
+        // and Friday morning belongs to Thursday
+        assert!(!friday_night.is_active(at(5, 6, 0)));
+    }
+
+    #[test]
+    fn window_across_week_end() {
+        let sunday_night = quiet_hours(&[Weekday::Sun], "23:00", "01:00");
+        assert!(sunday_night.is_active(at(7, 23, 30)));
+        assert!(sunday_night.is_active(at(8, 0, 30)));
+        assert!(!sunday_night.is_active(at(7, 0, 30)));
+    }
+
+    #[test]
+    fn days_limit_window_within_day() {
+        let weekdays = quiet_hours(
+            &[
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            "09:00",
+            "17:00",
+        );
+        assert!(weekdays.is_active(at(1, 10, 0)));
+        assert!(weekdays.is_active(at(5, 16, 59)));
+        assert!(!weekdays.is_active(at(6, 10, 0)));
+        assert!(!weekdays.is_active(at(7, 10, 0)));
+    }
+}
@@ -0,0 +1,329 @@
+use std::time::Duration;
+
+#[allow(unused_imports)]
+use log::*;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::dbus::{Details, Urgency};
+
+/// Regular expression that can be read from config
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    fn is_match(&self, text: Option<&str>) -> bool {
+        text.is_some_and(|text| self.0.is_match(text))
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Regex::new(&s).map(Self).map_err(serde::de::Error::custom)
+    }
+}
+
+/// All specified fields must match. Empty matcher matches everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Matcher {
+    #[serde(default)]
+    pub app_name: Option<Pattern>,
+    #[serde(default)]
+    pub desktop_entry: Option<Pattern>,
+    #[serde(default)]
+    pub summary: Option<Pattern>,
+    #[serde(default)]
+    pub body: Option<Pattern>,
+    #[serde(default)]
+    pub category: Option<Pattern>,
+    #[serde(default)]
+    pub urgency: Option<Urgency>,
+}
+
+impl Matcher {
+    pub fn matches(&self, details: &Details) -> bool {
+        let check = |pattern: &Option<Pattern>, text: Option<&str>| {
+            pattern.as_ref().is_none_or(|p| p.is_match(text))
+        };
+
+        check(&self.app_name, details.app_name.as_deref())
+            && check(&self.desktop_entry, details.hints.desktop_entry.as_deref())
+            && check(&self.summary, Some(&details.summary))
+            && check(&self.body, details.body.as_deref())
+            && check(
+                &self.category,
+                details
+                    .hints
+                    .category
+                    .as_ref()
+                    .map(|c| c.to_string())
+                    .as_deref(),
+            )
+            && self.urgency.is_none_or(|u| u == details.hints.urgency)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// Used in logs only
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "match", default)]
+    pub matcher: Matcher,
+
+    /// In milliseconds. 0 means never expire
+    #[serde(default)]
+    pub expire_timeout: Option<u64>,
+    #[serde(default)]
+    pub urgency: Option<Urgency>,
+    /// Icon name or absolute path
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub css_class: Option<String>,
+    #[serde(default)]
+    pub hide_body: bool,
+    /// Not stored in history
+    #[serde(default)]
+    pub transient: bool,
+    /// Stays on screen after action is invoked
+    #[serde(default)]
+    pub resident: bool,
+    /// Group key. Notifications with the same key are grouped if grouping is enabled
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Notification is not shown and not stored at all
+    #[serde(default)]
+    pub drop: bool,
+}
+
+impl Rule {
+    fn name(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("#{}", index))
+    }
+}
+
+/// Applies every matching rule in order.
+/// Returns `None` if notification should be dropped,
+/// otherwise also tells whether expire timeout was set by some rule
+pub fn apply(rules: &[Rule], mut details: Details) -> Option<(Details, bool)> {
+    let mut expire_timeout_set = false;
+    for (index, rule) in rules.iter().enumerate() {
+        let name = rule.name(index);
+
+        if !rule.matcher.matches(&details) {
+            debug!(
+                "Rule {} doesn't match notification id: {}",
+                name, details.id
+            );
+            continue;
+        }
+        debug!("Rule {} matches notification id: {}", name, details.id);
+
+        if rule.drop {
+            debug!("Rule {}: dropping notification id: {}", name, details.id);
+            return None;
+        }
+
+        if let Some(timeout) = rule.expire_timeout {
+            debug!("Rule {}: expire timeout set to {}ms", name, timeout);
+            details.expire_timeout = if timeout == 0 {
+                Duration::MAX
+            } else {
+                Duration::from_millis(timeout)
+            };
+            expire_timeout_set = true;
+        }
+
+        if let Some(urgency) = rule.urgency {
+            debug!("Rule {}: urgency set to {:?}", name, urgency);
+            details.hints.urgency = urgency;
+        }
+
+        if let Some(icon) = &rule.icon {
+            debug!("Rule {}: icon set to {}", name, icon);
+            details.app_icon = Some(icon.clone());
+            details.hints.image_data = None;
+            details.hints.image_path = None;
+            details.hints.icon_data = None;
+        }
+
+        if let Some(css_class) = &rule.css_class {
+            debug!("Rule {}: css class {} added", name, css_class);
+            details.css_classes.push(css_class.clone());
+        }
+
+        if rule.hide_body {
+            debug!("Rule {}: body hidden", name);
+            details.body = None;
+        }
+
+        if let Some(group) = &rule.group {
+            debug!("Rule {}: group set to {}", name, group);
+            details.group = Some(group.clone());
+        }
+
+        if rule.transient {
+            debug!("Rule {}: marked as transient", name);
+            details.hints.transient = true;
+        }
+
+        if rule.resident {
+            debug!("Rule {}: marked as resident", name);
+            details.hints.resident = true;
+        }
+    }
+
+    Some((details, expire_timeout_set))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::dbus::{Category, Hints};
+
+    fn details() -> Details {
+        Details {
+            id: 1,
+            app_name: Some("Telegram".to_owned()),
+            app_icon: Some("telegram".to_owned()),
+            summary: "Alice".to_owned(),
+            body: Some("hello".to_owned()),
+            actions: Vec::new(),
+            hints: Hints {
+                category: Some(Category::from("im.received")),
+                image_path: Some("/tmp/alice.png".to_owned()),
+                ..Default::default()
+            },
+            expire_timeout: Duration::from_secs(5),
+            timestamp: OffsetDateTime::UNIX_EPOCH,
+            css_classes: Vec::new(),
+            group: None,
+        }
+    }
+
+    fn rules(s: &str) -> Vec<Rule> {
+        ron::from_str(s).unwrap()
+    }
+
+    fn matcher(s: &str) -> Matcher {
+        ron::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn empty_matcher_matches_everything() {
+        assert!(Matcher::default().matches(&details()));
+    }
+
+    #[test]
+    fn all_fields_must_match() {
+        let details = details();
+        assert!(matcher(r#"(app_name: Some("^Tele"), summary: Some("Alice"))"#).matches(&details));
+        assert!(!matcher(r#"(app_name: Some("^Tele"), summary: Some("Bob"))"#).matches(&details));
+        assert!(matcher(r#"(category: Some("^im\\.received$"))"#).matches(&details));
+        assert!(!matcher(r#"(category: Some("^email"))"#).matches(&details));
+        assert!(matcher("(urgency: Some(Normal))").matches(&details));
+        assert!(!matcher("(urgency: Some(Critical))").matches(&details));
+    }
+
+    #[test]
+    fn pattern_does_not_match_missing_field() {
+        let details = details();
+        assert!(!matcher(r#"(desktop_entry: Some(".*"))"#).matches(&details));
+
+        let details = Details {
+            body: None,
+            ..details
+        };
+        assert!(!matcher(r#"(body: Some(""))"#).matches(&details));
+    }
+
+    #[test]
+    fn invalid_pattern_is_config_error() {
+        assert!(ron::from_str::<Matcher>(r#"(summary: Some("("))"#).is_err());
+    }
+
+    #[test]
+    fn matching_rules_are_applied_in_order() {
+        let rules = rules(
+            r#"[
+                (
+                    match: (app_name: Some("^Telegram$")),
+                    expire_timeout: Some(3000),
+                    urgency: Some(Low),
+                    icon: Some("chat"),
+                    css_class: Some("chat"),
+                    hide_body: true,
+                    group: Some("chat"),
+                ),
+                (
+                    match: (app_name: Some("^Discord$")),
+                    css_class: Some("discord"),
+                ),
+                (
+                    match: (urgency: Some(Low)),
+                    css_class: Some("quiet"),
+                    transient: true,
+                    resident: true,
+                ),
+            ]"#,
+        );
+
+        let (details, expire_timeout_set) = apply(&rules, details()).unwrap();
+        assert!(expire_timeout_set);
+        assert_eq!(details.expire_timeout, Duration::from_millis(3000));
+        assert_eq!(details.hints.urgency, Urgency::Low);
+        assert_eq!(details.app_icon.as_deref(), Some("chat"));
+        assert_eq!(details.hints.image_path, None);
+        assert_eq!(details.css_classes, ["chat", "quiet"]);
+        assert_eq!(details.body, None);
+        assert_eq!(details.group.as_deref(), Some("chat"));
+        assert!(details.hints.transient);
+        assert!(details.hints.resident);
+    }
+
+    #[test]
+    fn timeout_is_reported_only_when_set() {
+        let urgency = rules("[(urgency: Some(Critical))]");
+        let (applied, expire_timeout_set) = apply(&urgency, details()).unwrap();
+        assert!(!expire_timeout_set);
+        assert_eq!(applied.expire_timeout, Duration::from_secs(5));
+
+        // same as default one, but still set explicitly
+        let timeout = rules("[(expire_timeout: Some(5000))]");
+        let (_, expire_timeout_set) = apply(&timeout, details()).unwrap();
+        assert!(expire_timeout_set);
+    }
+
+    #[test]
+    fn zero_timeout_never_expires() {
+        let rules = rules("[(expire_timeout: Some(0))]");
+        let (details, expire_timeout_set) = apply(&rules, details()).unwrap();
+        assert!(expire_timeout_set);
+        assert_eq!(details.expire_timeout, Duration::MAX);
+    }
+
+    #[test]
+    fn only_matching_rule_drops() {
+        let drop = rules(r#"[(match: (summary: Some("Alice")), drop: true)]"#);
+        assert_eq!(apply(&drop, details()), None);
+
+        let keep = rules(r#"[(match: (summary: Some("Bob")), drop: true)]"#);
+        assert_eq!(apply(&keep, details()), Some((details(), false)));
+    }
+}
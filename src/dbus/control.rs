@@ -0,0 +1,179 @@
+use futures::channel::{mpsc, oneshot};
+#[allow(unused_imports)]
+use log::*;
+use serde::{Deserialize, Serialize};
+use zbus::{fdo, interface, object_server::SignalContext, zvariant::Type};
+
+use crate::history::Entry;
+
+use super::Message;
+
+/// Notification as it is exposed to the control clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct NotificationInfo {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    /// 0 - low, 1 - normal, 2 - critical
+    pub urgency: u8,
+    /// (key, text) pairs
+    pub actions: Vec<(String, String)>,
+    /// Unix timestamp of notification arrival
+    pub created: i64,
+    /// Close reason. 0 if notification is still open or was never shown
+    pub reason: u32,
+}
+
+impl From<&Entry> for NotificationInfo {
+    fn from(value: &Entry) -> Self {
+        Self {
+            id: value.id,
+            app_name: value.app_name.clone().unwrap_or_default(),
+            summary: strip_markup(&value.summary),
+            body: value.body.as_deref().map(strip_markup).unwrap_or_default(),
+            urgency: value.urgency as u8,
+            actions: value.actions.clone(),
+            created: value.created,
+            reason: value.reason.map_or(0, |r| r as u32),
+        }
+    }
+}
+
+fn strip_markup(text: &str) -> String {
+    gtk::pango::parse_markup(text, '\0')
+        .map(|(_, text, _)| text.to_string())
+        .unwrap_or_else(|_| text.to_owned())
+}
+
+/// Requests from control interface that should be processed on the main thread.
+/// Each one carries sender for the result
+#[derive(Debug)]
+pub enum Request {
+    List(oneshot::Sender<Vec<NotificationInfo>>),
+    /// Optionally filtered by category or category class
+    History(Option<String>, oneshot::Sender<Vec<NotificationInfo>>),
+    Dismiss(u32, oneshot::Sender<bool>),
+    DismissAll(oneshot::Sender<u32>),
+    InvokeAction(u32, String, oneshot::Sender<bool>),
+    SetDnd(Option<bool>, oneshot::Sender<bool>),
+    /// Output connector name and whether fullscreen client is there
+    SetFullscreen(String, bool, oneshot::Sender<()>),
+    Reload(oneshot::Sender<Result<(), String>>),
+    ToggleCenter(oneshot::Sender<()>),
+}
+
+#[derive(Debug)]
+pub struct Control {
+    sender: mpsc::Sender<Message>,
+    /// Updated from the main thread
+    pub quiet_hours: bool,
+}
+
+impl Control {
+    pub fn new(sender: mpsc::Sender<Message>) -> Self {
+        Self {
+            sender,
+            quiet_hours: false,
+        }
+    }
+
+    async fn request<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<T>) -> Request,
+    ) -> fdo::Result<T> {
+        let (tx, rx) = oneshot::channel();
+
+        self.sender
+            .clone()
+            .try_send(Message::Control(request(tx)))
+            .map_err(|e| {
+                error!("Failed to send control message: {}", e);
+                fdo::Error::Failed(e.to_string())
+            })?;
+
+        rx.await
+            .map_err(|_| fdo::Error::Failed("Request was dropped by daemon".to_owned()))
+    }
+}
+
+#[interface(name = "com.bzglve.rustyfications.Control")]
+impl Control {
+    /// Notifications that are currently on screen
+    async fn list(&self) -> fdo::Result<Vec<NotificationInfo>> {
+        debug!("Control: list");
+        self.request(Request::List).await
+    }
+
+    /// Stored notifications from oldest to newest
+    async fn history(&self) -> fdo::Result<Vec<NotificationInfo>> {
+        debug!("Control: history");
+        self.request(|tx| Request::History(None, tx)).await
+    }
+
+    /// Stored notifications with given category (`email.arrived`) or category class (`email`)
+    async fn history_by_category(&self, category: String) -> fdo::Result<Vec<NotificationInfo>> {
+        debug!("Control: history by category {}", category);
+        self.request(|tx| Request::History(Some(category), tx))
+            .await
+    }
+
+    /// Returns `false` if there is no such notification
+    async fn dismiss(&self, id: u32) -> fdo::Result<bool> {
+        debug!("Control: dismiss {}", id);
+        self.request(|tx| Request::Dismiss(id, tx)).await
+    }
+
+    /// Returns number of dismissed notifications
+    async fn dismiss_all(&self) -> fdo::Result<u32> {
+        debug!("Control: dismiss all");
+        self.request(Request::DismissAll).await
+    }
+
+    /// Returns `false` if there is no such notification or action
+    async fn invoke_action(&self, id: u32, key: String) -> fdo::Result<bool> {
+        debug!("Control: invoke action {} of {}", key, id);
+        self.request(|tx| Request::InvokeAction(id, key, tx)).await
+    }
+
+    /// Returns new do-not-disturb state
+    async fn set_dnd(&self, enabled: bool) -> fdo::Result<bool> {
+        debug!("Control: set dnd {}", enabled);
+        self.request(|tx| Request::SetDnd(Some(enabled), tx)).await
+    }
+
+    /// Returns new do-not-disturb state
+    async fn toggle_dnd(&self) -> fdo::Result<bool> {
+        debug!("Control: toggle dnd");
+        self.request(|tx| Request::SetDnd(None, tx)).await
+    }
+
+    /// Reports whether there is a fullscreen client on output with given connector name
+    /// (`DP-1`). Empty name is for unknown output. Expected to be called by compositor scripts
+    async fn set_fullscreen(&self, output: String, active: bool) -> fdo::Result<()> {
+        debug!("Control: set fullscreen {} on {:?}", active, output);
+        self.request(|tx| Request::SetFullscreen(output, active, tx))
+            .await
+    }
+
+    async fn reload(&self) -> fdo::Result<()> {
+        debug!("Control: reload");
+        self.request(Request::Reload)
+            .await?
+            .map_err(fdo::Error::Failed)
+    }
+
+    async fn toggle_center(&self) -> fdo::Result<()> {
+        debug!("Control: toggle center");
+        self.request(Request::ToggleCenter).await
+    }
+
+    /// Whether scheduled quiet hours are active now
+    #[zbus(property)]
+    fn quiet_hours(&self) -> bool {
+        self.quiet_hours
+    }
+
+    #[zbus(signal)]
+    pub async fn quiet_hours_toggled(ctxt: &SignalContext<'_>, active: bool) -> zbus::Result<()>;
+}
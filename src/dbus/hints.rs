@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
+pub use category::Category;
 pub use idata::IData;
+pub use urgency::Urgency;
 use zbus::zvariant::OwnedValue as Value;
 
 mod idata {
@@ -63,22 +65,155 @@ mod idata {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+mod category {
+    use std::fmt;
+
+    /// Spec category like `email.arrived`. Subtype is optional
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    pub struct Category {
+        pub class: String,
+        pub subtype: Option<String>,
+    }
+
+    impl Category {
+        /// `category-email` and `category-email-arrived` for `email.arrived`
+        pub fn css_classes(&self) -> Vec<String> {
+            let sanitize = |s: &str| -> String {
+                s.chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                    .collect()
+            };
+
+            let class = format!("category-{}", sanitize(&self.class));
+            match &self.subtype {
+                Some(subtype) => vec![class.clone(), format!("{}-{}", class, sanitize(subtype))],
+                None => vec![class],
+            }
+        }
+
+        /// Whether `name` is either full category or its class
+        pub fn matches(&self, name: &str) -> bool {
+            name == self.class || name == self.to_string()
+        }
+    }
+
+    impl From<&str> for Category {
+        fn from(value: &str) -> Self {
+            match value.split_once('.') {
+                Some((class, subtype)) => Self {
+                    class: class.to_owned(),
+                    subtype: (!subtype.is_empty()).then(|| subtype.to_owned()),
+                },
+                None => Self {
+                    class: value.to_owned(),
+                    subtype: None,
+                },
+            }
+        }
+    }
+
+    impl fmt::Display for Category {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.subtype {
+                Some(subtype) => write!(f, "{}.{}", self.class, subtype),
+                None => write!(f, "{}", self.class),
+            }
+        }
+    }
+}
+
+mod urgency {
+    use serde::{Deserialize, Serialize};
+    use zbus::zvariant::OwnedValue as Value;
+
+    #[derive(
+        Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+    )]
+    #[repr(u8)]
+    pub enum Urgency {
+        Low = 0,
+        #[default]
+        Normal = 1,
+        Critical = 2,
+    }
+
+    impl Urgency {
+        pub fn css_class(&self) -> &'static str {
+            match self {
+                Self::Low => "urgency-low",
+                Self::Normal => "urgency-normal",
+                Self::Critical => "urgency-critical",
+            }
+        }
+    }
+
+    impl From<u8> for Urgency {
+        fn from(value: u8) -> Self {
+            match value {
+                0 => Self::Low,
+                2 => Self::Critical,
+                _ => Self::Normal,
+            }
+        }
+    }
+
+    impl From<&Value> for Urgency {
+        // spec says it is a byte, but some clients send it as an integer
+        fn from(value: &Value) -> Self {
+            u8::try_from(value)
+                .ok()
+                .or_else(|| i32::try_from(value).ok().and_then(|v| u8::try_from(v).ok()))
+                .or_else(|| u32::try_from(value).ok().and_then(|v| u8::try_from(v).ok()))
+                .map(Self::from)
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Hints {
     pub action_icons: bool,
-    // category
+    pub category: Option<Category>,
     pub desktop_entry: Option<String>,
     pub image_data: Option<IData>,
     pub image_path: Option<String>,
     pub icon_data: Option<IData>,
-    // resident
-    // sound-file
-    // sound-name
-    // suppress-sound
-    // transient
-    // x
-    // y
-    // urgency
+    /// Not closed after action is invoked
+    pub resident: bool,
+    /// Path to the sound file
+    pub sound_file: Option<String>,
+    /// Name from freedesktop sound theme
+    pub sound_name: Option<String>,
+    pub suppress_sound: bool,
+    /// Not stored in history
+    pub transient: bool,
+    pub urgency: Urgency,
+    /// Progress in percents
+    pub value: Option<u8>,
+    /// Screen coordinates popup should point to. Set only if both are present
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+// spec says it is a boolean, but some clients send it as an integer
+fn flag(value: Option<Value>) -> bool {
+    value.is_some_and(|v| {
+        bool::try_from(&v)
+            .ok()
+            .or_else(|| i32::try_from(&v).ok().map(|v| v != 0))
+            .or_else(|| u8::try_from(&v).ok().map(|v| v != 0))
+            .unwrap_or(false)
+    })
+}
+
+// spec says it is an integer, but clients use different integer types
+fn int(value: Option<Value>) -> Option<i32> {
+    value.and_then(|v| {
+        i32::try_from(&v)
+            .ok()
+            .or_else(|| u32::try_from(&v).ok().and_then(|v| i32::try_from(v).ok()))
+            .or_else(|| u8::try_from(&v).ok().map(i32::from))
+    })
 }
 
 impl From<HashMap<&str, Value>> for Hints {
@@ -88,6 +223,12 @@ impl From<HashMap<&str, Value>> for Hints {
             .and_then(|v| v.try_into().ok())
             .unwrap_or(false);
 
+        let category = value
+            .remove("category")
+            .and_then(|v| String::try_from(v).ok())
+            .filter(|s| !s.is_empty())
+            .map(|s| Category::from(s.as_str()));
+
         let desktop_entry = {
             let v: Option<String> = value
                 .remove("desktop-entry")
@@ -124,12 +265,192 @@ impl From<HashMap<&str, Value>> for Hints {
         };
         let icon_data = value.remove("icon_data").and_then(|v| v.try_into().ok());
 
+        let resident = flag(value.remove("resident"));
+        let sound_file = value
+            .remove("sound-file")
+            .and_then(|v| String::try_from(v).ok())
+            .filter(|s| !s.is_empty());
+        let sound_name = value
+            .remove("sound-name")
+            .and_then(|v| String::try_from(v).ok())
+            .filter(|s| !s.is_empty());
+        let suppress_sound = flag(value.remove("suppress-sound"));
+        let transient = flag(value.remove("transient"));
+
+        let urgency = value
+            .remove("urgency")
+            .map(|v| Urgency::from(&v))
+            .unwrap_or_default();
+
+        let (x, y) = match (int(value.remove("x")), int(value.remove("y"))) {
+            (Some(x), Some(y)) => (Some(x), Some(y)),
+            _ => (None, None),
+        };
+
+        // spec says it is in 0-100 range
+        let value = int(value.remove("value")).map(|v| v.clamp(0, 100) as u8);
+
         Self {
             action_icons,
+            category,
             desktop_entry,
             image_data,
             image_path,
             icon_data,
+            resident,
+            sound_file,
+            sound_name,
+            suppress_sound,
+            urgency,
+            transient,
+            value,
+            x,
+            y,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::Str;
+
+    use super::*;
+
+    fn hints<const N: usize>(values: [(&'static str, Value); N]) -> Hints {
+        Hints::from(HashMap::from(values))
+    }
+
+    fn string(s: &str) -> Value {
+        Value::from(Str::from(s.to_owned()))
+    }
+
+    #[test]
+    fn empty_hints_are_default() {
+        assert_eq!(hints([]), Hints::default());
+    }
+
+    #[test]
+    fn urgency_byte_or_integer() {
+        assert_eq!(hints([("urgency", Value::from(0u8))]).urgency, Urgency::Low);
+        assert_eq!(
+            hints([("urgency", Value::from(2u8))]).urgency,
+            Urgency::Critical
+        );
+        assert_eq!(
+            hints([("urgency", Value::from(2i32))]).urgency,
+            Urgency::Critical
+        );
+        assert_eq!(
+            hints([("urgency", Value::from(0u32))]).urgency,
+            Urgency::Low
+        );
+        assert_eq!(
+            hints([("urgency", Value::from(7u8))]).urgency,
+            Urgency::Normal
+        );
+        assert_eq!(
+            hints([("urgency", string("critical"))]).urgency,
+            Urgency::Normal
+        );
+    }
+
+    #[test]
+    fn flags_boolean_or_integer() {
+        for key in ["resident", "suppress-sound", "transient"] {
+            let get = |hints: Hints| match key {
+                "resident" => hints.resident,
+                "suppress-sound" => hints.suppress_sound,
+                _ => hints.transient,
+            };
+            assert!(get(hints([(key, Value::from(true))])));
+            assert!(get(hints([(key, Value::from(1i32))])));
+            assert!(get(hints([(key, Value::from(1u8))])));
+            assert!(!get(hints([(key, Value::from(false))])));
+            assert!(!get(hints([(key, Value::from(0i32))])));
+            assert!(!get(hints([(key, string("true"))])));
+        }
+    }
+
+    #[test]
+    fn value_is_clamped() {
+        assert_eq!(hints([("value", Value::from(42i32))]).value, Some(42));
+        assert_eq!(hints([("value", Value::from(42u32))]).value, Some(42));
+        assert_eq!(hints([("value", Value::from(150i32))]).value, Some(100));
+        assert_eq!(hints([("value", Value::from(-5i32))]).value, Some(0));
+        assert_eq!(hints([("value", string("42"))]).value, None);
+    }
+
+    #[test]
+    fn position_needs_both_coordinates() {
+        let position = hints([("x", Value::from(10i32)), ("y", Value::from(20u32))]);
+        assert_eq!((position.x, position.y), (Some(10), Some(20)));
+
+        let position = hints([("x", Value::from(10i32))]);
+        assert_eq!((position.x, position.y), (None, None));
+
+        let position = hints([("y", Value::from(20u8))]);
+        assert_eq!((position.x, position.y), (None, None));
+    }
+
+    #[test]
+    fn category() {
+        assert_eq!(
+            hints([("category", string("email.arrived"))]).category,
+            Some(Category {
+                class: "email".to_owned(),
+                subtype: Some("arrived".to_owned()),
+            })
+        );
+        assert_eq!(
+            hints([("category", string("device"))]).category,
+            Some(Category {
+                class: "device".to_owned(),
+                subtype: None,
+            })
+        );
+        assert_eq!(hints([("category", string(""))]).category, None);
+    }
+
+    #[test]
+    fn category_format() {
+        let category = Category::from("email.arrived");
+        assert_eq!(category.to_string(), "email.arrived");
+        assert_eq!(
+            category.css_classes(),
+            ["category-email", "category-email-arrived"]
+        );
+        assert_eq!(Category::from("email.").to_string(), "email");
+    }
+
+    #[test]
+    fn strings_ignore_empty_values() {
+        let full = hints([
+            ("desktop-entry", string("firefox")),
+            ("image-path", string("/tmp/image.png")),
+            ("sound-file", string("/tmp/bell.oga")),
+            ("sound-name", string("message-new-instant")),
+        ]);
+        assert_eq!(full.desktop_entry.as_deref(), Some("firefox"));
+        assert_eq!(full.image_path.as_deref(), Some("/tmp/image.png"));
+        assert_eq!(full.sound_file.as_deref(), Some("/tmp/bell.oga"));
+        assert_eq!(full.sound_name.as_deref(), Some("message-new-instant"));
+
+        let empty = hints([
+            ("desktop-entry", string("")),
+            ("image-path", string("")),
+            ("sound-file", string("")),
+            ("sound-name", string("")),
+        ]);
+        assert_eq!(empty, Hints::default());
+    }
+
+    #[test]
+    fn deprecated_image_path_name() {
+        assert_eq!(
+            hints([("image_path", string("/tmp/image.png"))])
+                .image_path
+                .as_deref(),
+            Some("/tmp/image.png")
+        );
+    }
+}
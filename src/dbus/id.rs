@@ -9,6 +9,10 @@ impl Id {
         ID.load(Ordering::Relaxed)
     }
 
+    pub fn init_glob(value: u32) {
+        ID.store(value, Ordering::Relaxed)
+    }
+
     pub fn bump_glob() -> u32 {
         ID.fetch_add(1, Ordering::Relaxed) + 1
     }
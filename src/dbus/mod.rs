@@ -1,4 +1,5 @@
 mod action;
+mod control;
 mod hints;
 mod id;
 mod server_info;
@@ -6,12 +7,14 @@ mod server_info;
 use std::{cmp::Ordering, collections::HashMap, time::Duration, vec};
 
 pub use action::Action;
+pub use control::{Control, NotificationInfo, Request};
 use futures::channel::mpsc;
-pub use hints::Hints;
+pub use hints::{Category, Hints, Urgency};
 pub use id::Id;
 #[allow(unused_imports)]
 use log::*;
 pub use server_info::ServerInfo;
+use time::OffsetDateTime;
 pub use zbus::blocking::object_server::InterfaceRef;
 use zbus::{
     blocking::connection::Builder as ConnectionBuilder,
@@ -20,7 +23,7 @@ use zbus::{
     zvariant::{OwnedValue as Value, Type},
 };
 
-use crate::config::CONFIG;
+use crate::config::{rules, CONFIG};
 
 static BUS_NAME: &str = "org.freedesktop.Notifications";
 static BUS_OBJECT_PATH: &str = "/org/freedesktop/Notifications";
@@ -35,6 +38,11 @@ pub struct Details {
     pub actions: Vec<Action>,
     pub hints: Hints,
     pub expire_timeout: Duration,
+    pub timestamp: OffsetDateTime,
+    /// Added by rules
+    pub css_classes: Vec<String>,
+    /// Group key set by rules
+    pub group: Option<String>,
 }
 
 #[derive(Debug)]
@@ -42,10 +50,11 @@ pub enum Message {
     New(Details),
     Replace(Details),
     Close(u32),
+    Control(Request),
 }
 
 /// The reason the notification was closed
-#[derive(serde::Serialize, Type, Debug, Clone, Copy)]
+#[derive(serde::Serialize, serde::Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum Reason {
     /// The notification expired
@@ -73,22 +82,30 @@ impl IFace {
         // e.g. disable sound or icons
         // all other stuff that implemented one of capabilities should also check and skip processing if off
         debug!("Getting capabilities");
-        vec![
+        let mut capabilities = vec![
             // freedesktop
             "action-icons",
             "actions",
             "body",
             "body-hyperlinks",
-            // "body-images",
             "body-markup",
             // "icon-multi",
             "icon-static",
-            // "persistence",
-            // "sound",
-
             // custom but known
             "inline-reply",
-        ]
+        ];
+
+        if CONFIG.lock().unwrap().history.enabled {
+            capabilities.push("persistence");
+        }
+        if CONFIG.lock().unwrap().body_images.enabled {
+            capabilities.push("body-images");
+        }
+        if !CONFIG.lock().unwrap().sound.mute {
+            capabilities.push("sound");
+        }
+
+        capabilities
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -118,6 +135,13 @@ impl IFace {
         let hints: Hints = Hints::from(hints);
         debug!("Processed hints: {:?}", hints);
 
+        let category_defaults = CONFIG
+            .lock()
+            .unwrap()
+            .category_defaults(hints.category.as_ref());
+        let has_image =
+            hints.image_data.is_some() || hints.image_path.is_some() || hints.icon_data.is_some();
+
         let details = Details {
             id: notification_id,
             app_name: if app_name.is_empty() {
@@ -125,10 +149,12 @@ impl IFace {
             } else {
                 Some(app_name.to_owned())
             },
-            app_icon: if app_icon.is_empty() {
-                None
-            } else {
+            app_icon: if !app_icon.is_empty() {
                 Some(app_icon.to_owned())
+            } else if !has_image {
+                category_defaults.icon
+            } else {
+                None
             },
             // TODO if markup feature is enabled
             summary: format!("<b>{}</b>", summary.replace("&", "&amp;")),
@@ -142,13 +168,27 @@ impl IFace {
                 .chunks_exact(2)
                 .map(|t| Action::new(t[0], t[1]))
                 .collect(),
-            hints,
             expire_timeout: match expire_timeout.cmp(&0) {
-                Ordering::Less => Duration::from_millis(CONFIG.lock().unwrap().expire_timeout),
+                Ordering::Less => CONFIG.lock().unwrap().hints_expire_timeout(&hints),
                 Ordering::Equal => Duration::MAX,
                 Ordering::Greater => Duration::from_millis(expire_timeout as u64),
             },
+            hints,
+            timestamp: OffsetDateTime::now_utc(),
+            css_classes: Vec::new(),
+            group: None,
+        };
+
+        let urgency = details.hints.urgency;
+        let rules = CONFIG.lock().unwrap().rules.clone();
+        let Some((mut details, expire_timeout_set)) = rules::apply(&rules, details) else {
+            info!("Notification with id: {} dropped by rules", notification_id);
+            return notification_id;
         };
+        // default timeout depends on urgency that could be changed by rules
+        if expire_timeout < 0 && !expire_timeout_set && details.hints.urgency != urgency {
+            details.expire_timeout = CONFIG.lock().unwrap().hints_expire_timeout(&details.hints);
+        }
 
         if notification_id != replaces_id {
             if let Err(e) = self.sender.try_send(Message::New(details)) {
@@ -220,16 +260,20 @@ impl IFace {
         }
     }
 
-    pub fn connect(self) -> Result<InterfaceRef<Self>, zbus::Error> {
+    pub fn connect(self) -> Result<(IFaceRef, ControlRef), zbus::Error> {
         info!("Establishing connection to the DBus interface");
+        let control = Control::new(self.sender.clone());
         let connection = ConnectionBuilder::session()?
             .name(BUS_NAME)?
             .serve_at(BUS_OBJECT_PATH, self)?
+            .serve_at(BUS_OBJECT_PATH, control)?
             .build()?;
 
         let i = connection.object_server().interface(BUS_OBJECT_PATH)?;
-        Ok(i)
+        let c = connection.object_server().interface(BUS_OBJECT_PATH)?;
+        Ok((i, c))
     }
 }
 
 pub type IFaceRef = InterfaceRef<IFace>;
+pub type ControlRef = InterfaceRef<Control>;
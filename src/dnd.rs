@@ -0,0 +1,229 @@
+use std::{rc::Rc, time::Duration};
+
+use gtk::glib;
+#[allow(unused_imports)]
+use log::*;
+use time::OffsetDateTime;
+
+use crate::{
+    config::{quiet_hours, CONFIG},
+    dbus::{Control, ControlRef, Details, Hints, IFaceRef, Id, Reason, Urgency},
+    gui::center,
+    history::Entry,
+    show_notification,
+    state::State,
+    types::RuntimeData,
+    utils::close_queued,
+};
+
+fn is_active(runtime_data: &RuntimeData) -> bool {
+    let runtime_data = runtime_data.borrow();
+    runtime_data.dnd || runtime_data.quiet_hours
+}
+
+/// Whether popup for the notification should not be shown
+pub fn suppresses(details: &Details, runtime_data: &RuntimeData) -> bool {
+    if !is_active(runtime_data) {
+        return false;
+    }
+
+    let bypass =
+        details.hints.urgency == Urgency::Critical && CONFIG.lock().unwrap().dnd.critical_bypass;
+    if bypass {
+        debug!(
+            "Critical notification with id: {} bypasses do-not-disturb",
+            details.id
+        );
+    }
+    !bypass
+}
+
+/// Puts notification into history instead of showing it
+pub fn queue(details: Details, runtime_data: &RuntimeData) {
+    info!(
+        "Do-not-disturb is on. Notification with id: {} is queued",
+        details.id
+    );
+
+    {
+        let mut runtime_data = runtime_data.borrow_mut();
+        if !details.hints.transient {
+            runtime_data.history.push(Entry::new(&details, None));
+        }
+        runtime_data.dnd_queue.push(details);
+    }
+    center::refresh(runtime_data);
+}
+
+pub fn is_queued(id: u32, runtime_data: &RuntimeData) -> bool {
+    runtime_data.borrow().dnd_queue.iter().any(|d| d.id == id)
+}
+
+/// Replaces queued notification in place along with its history entry
+pub fn replace(details: Details, runtime_data: &RuntimeData) {
+    {
+        let mut runtime_data = runtime_data.borrow_mut();
+        let Some(index) = runtime_data
+            .dnd_queue
+            .iter()
+            .position(|d| d.id == details.id)
+        else {
+            return;
+        };
+        if !details.hints.transient {
+            runtime_data.history.push(Entry::new(&details, None));
+        }
+        runtime_data.dnd_queue[index] = details;
+    }
+    center::refresh(runtime_data);
+}
+
+/// Removes queued notification. Returns `false` if there is no such
+pub fn remove(id: u32, reason: Reason, iface: Rc<IFaceRef>, runtime_data: &RuntimeData) -> bool {
+    let details = {
+        let mut runtime_data = runtime_data.borrow_mut();
+        let index = runtime_data.dnd_queue.iter().position(|d| d.id == id);
+        index.map(|index| runtime_data.dnd_queue.remove(index))
+    };
+    let Some(details) = details else {
+        return false;
+    };
+
+    close_queued(&details, reason, iface, runtime_data);
+    center::refresh(runtime_data);
+    true
+}
+
+pub fn set(
+    enabled: bool,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) {
+    let was_active = is_active(&runtime_data);
+    runtime_data.borrow_mut().dnd = enabled;
+    info!("Do-not-disturb: {}", enabled);
+
+    if CONFIG.lock().unwrap().dnd.persist {
+        State { dnd: enabled }.save();
+    }
+
+    if was_active {
+        release_queue(application, iface, runtime_data);
+    }
+}
+
+fn set_quiet_hours(
+    active: bool,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) {
+    let was_active = is_active(&runtime_data);
+    runtime_data.borrow_mut().quiet_hours = active;
+    info!("Quiet hours: {}", active);
+
+    if was_active {
+        release_queue(application, iface, runtime_data);
+    }
+}
+
+/// Shows summary of queued notifications if popups are not suppressed anymore
+fn release_queue(application: gtk::Application, iface: Rc<IFaceRef>, runtime_data: RuntimeData) {
+    if is_active(&runtime_data) {
+        return;
+    }
+
+    let queued = std::mem::take(&mut runtime_data.borrow_mut().dnd_queue);
+    if queued.is_empty() {
+        return;
+    }
+
+    // popups are not shown anymore, clients shouldn't keep waiting for them
+    for details in queued.iter() {
+        close_queued(details, Reason::Expired, iface.clone(), &runtime_data);
+    }
+    center::refresh(&runtime_data);
+
+    if CONFIG.lock().unwrap().dnd.summary {
+        show_summary(queued, application, iface, runtime_data);
+    }
+}
+
+/// Periodically checks configured quiet hours and reports changes over D-Bus
+pub fn watch_quiet_hours(
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    control: Rc<ControlRef>,
+    runtime_data: RuntimeData,
+) {
+    let check = move || {
+        let active = quiet_hours::any_active(&CONFIG.lock().unwrap().quiet_hours);
+        if active == runtime_data.borrow().quiet_hours {
+            return;
+        }
+
+        set_quiet_hours(
+            active,
+            application.clone(),
+            iface.clone(),
+            runtime_data.clone(),
+        );
+
+        let control = control.clone();
+        glib::spawn_future_local(async move {
+            let ctxt = control.signal_context();
+            let mut control_iface = control.get_mut();
+            control_iface.quiet_hours = active;
+
+            if let Err(e) = control_iface.quiet_hours_changed(ctxt).await {
+                error!("Failed to emit quiet hours property change: {}", e);
+            }
+            if let Err(e) = Control::quiet_hours_toggled(ctxt, active).await {
+                error!("Failed to emit quiet hours signal: {}", e);
+            }
+        });
+    };
+
+    check();
+    glib::timeout_add_local(Duration::from_secs(30), move || {
+        check();
+        glib::ControlFlow::Continue
+    });
+}
+
+fn show_summary(
+    queued: Vec<Details>,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) {
+    let mut apps: Vec<&str> = Vec::new();
+    for app_name in queued.iter().filter_map(|d| d.app_name.as_deref()) {
+        if !apps.contains(&app_name) {
+            apps.push(app_name);
+        }
+    }
+
+    let details = Details {
+        id: Id::bump_glob(),
+        app_name: Some(env!("CARGO_PKG_NAME").to_owned()),
+        app_icon: None,
+        summary: format!(
+            "<b>{} notification(s) arrived while in do-not-disturb</b>",
+            queued.len()
+        ),
+        body: (!apps.is_empty()).then(|| glib::markup_escape_text(&apps.join(", ")).to_string()),
+        actions: Vec::new(),
+        hints: Hints::default(),
+        expire_timeout: CONFIG
+            .lock()
+            .unwrap()
+            .default_expire_timeout(Urgency::Normal),
+        timestamp: OffsetDateTime::now_utc(),
+        css_classes: Vec::new(),
+        group: None,
+    };
+
+    show_notification(details, application, iface, runtime_data);
+}
@@ -0,0 +1,142 @@
+//! There is no common Wayland protocol to find out whether fullscreen client is shown
+//! on some output, so fullscreen state is reported per output from outside
+//! over control interface (e.g. by compositor script). See README for the contract
+
+use std::rc::Rc;
+
+use gtk::prelude::*;
+#[allow(unused_imports)]
+use log::*;
+
+use crate::{
+    config::{
+        layer::{Fullscreen, Layer},
+        CONFIG,
+    },
+    dbus::{Details, IFaceRef, Reason, Urgency},
+    gui::{overflow, utils::layer_update},
+    new_notification,
+    types::RuntimeData,
+    utils::close_queued,
+};
+
+/// Whether fullscreen client is reported on popups output
+pub fn is_active(runtime_data: &RuntimeData) -> bool {
+    let outputs = &runtime_data.borrow().fullscreen_outputs;
+    if outputs.contains("") {
+        return true;
+    }
+
+    let output = CONFIG.lock().unwrap().output.clone();
+    match output.monitor().and_then(|m| m.connector()) {
+        Some(connector) => outputs.contains(connector.as_str()),
+        None => !outputs.is_empty(),
+    }
+}
+
+/// Whether notification should wait until fullscreen client is gone
+pub fn suppresses(details: &Details, runtime_data: &RuntimeData) -> bool {
+    applies(Fullscreen::Queue, details.hints.urgency) && is_active(runtime_data)
+}
+
+/// Whether popup of given urgency should be hidden. `active` is result of `is_active`
+pub fn hides(urgency: Urgency, active: bool) -> bool {
+    active && applies(Fullscreen::Hide, urgency)
+}
+
+/// Popups on `Overlay` layer are out of policy
+fn applies(policy: Fullscreen, urgency: Urgency) -> bool {
+    let config = CONFIG.lock().unwrap();
+    config.fullscreen == policy && config.layer(urgency, false) != Layer::Overlay
+}
+
+pub fn queue(details: Details, runtime_data: &RuntimeData) {
+    info!(
+        "Fullscreen client is active. Notification with id: {} is queued",
+        details.id
+    );
+    runtime_data.borrow_mut().fullscreen_queue.push(details);
+}
+
+pub fn is_queued(id: u32, runtime_data: &RuntimeData) -> bool {
+    runtime_data
+        .borrow()
+        .fullscreen_queue
+        .iter()
+        .any(|d| d.id == id)
+}
+
+/// Replaces queued notification in place
+pub fn replace(details: Details, runtime_data: &RuntimeData) {
+    let mut runtime_data = runtime_data.borrow_mut();
+    if let Some(queued) = runtime_data
+        .fullscreen_queue
+        .iter_mut()
+        .find(|d| d.id == details.id)
+    {
+        *queued = details;
+    }
+}
+
+/// Removes queued notification. Returns `false` if there is no such
+pub fn remove(id: u32, reason: Reason, iface: Rc<IFaceRef>, runtime_data: &RuntimeData) -> bool {
+    let details = {
+        let mut runtime_data = runtime_data.borrow_mut();
+        let index = runtime_data
+            .fullscreen_queue
+            .iter()
+            .position(|d| d.id == id);
+        index.map(|index| runtime_data.fullscreen_queue.remove(index))
+    };
+    let Some(details) = details else {
+        return false;
+    };
+
+    close_queued(&details, reason, iface, runtime_data);
+    true
+}
+
+/// Fullscreen state of the output is reported from outside. See module docs
+pub fn set(
+    output: String,
+    active: bool,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) {
+    info!("Fullscreen on output {:?}: {}", output, active);
+    let was_active = is_active(&runtime_data);
+    {
+        let outputs = &mut runtime_data.borrow_mut().fullscreen_outputs;
+        if active {
+            outputs.insert(output);
+        } else {
+            outputs.remove(&output);
+        }
+    }
+
+    let active = is_active(&runtime_data);
+    if active == was_active {
+        return;
+    }
+    debug!("Fullscreen on popups output: {}", active);
+
+    layer_update(&runtime_data);
+    if active {
+        return;
+    }
+
+    let queued = std::mem::take(&mut runtime_data.borrow_mut().fullscreen_queue);
+    for details in queued {
+        if overflow::should_queue(&details, &runtime_data) {
+            overflow::queue(details, &runtime_data);
+        } else {
+            new_notification(
+                details,
+                application.clone(),
+                iface.clone(),
+                runtime_data.clone(),
+            );
+        }
+    }
+}
@@ -0,0 +1,670 @@
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc, sync::LazyLock};
+
+use gtk::{
+    gdk,
+    gdk_pixbuf::Pixbuf,
+    gio,
+    glib::{self, clone},
+    pango::{self, EllipsizeMode},
+    prelude::*,
+    Align, Justification, Orientation,
+};
+#[allow(unused_imports)]
+use log::*;
+use regex::{Captures, Regex};
+
+use crate::{
+    config::{body_images::BodyImages, CONFIG},
+    dbus::{Action, Details, IFace, IFaceRef, Urgency},
+    hooks::{self, Event},
+};
+
+use super::utils::pixbuf;
+
+/// `<img>` tag with its attributes captured
+static IMG_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<img\b([^>]*?)/?>").unwrap());
+static IMG_ATTR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\b(src|alt)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+/// Widgets tree of a single notification.
+///
+/// Shared between popups and notification center so user styles applies to both
+#[derive(Clone)]
+pub struct Card {
+    pub id: u32,
+    app_name: gtk::Label,
+    icon: gtk::Image,
+    summary: gtk::Label,
+    app_icon: gtk::Image,
+    body: gtk::Label,
+    body_images: gtk::Box,
+    progress: gtk::ProgressBar,
+    reply_entry: gtk::Entry,
+    reply_revealer: gtk::Revealer,
+    actions_box: gtk::Box,
+    /// Classes added by rules
+    css_classes: Rc<RefCell<Vec<String>>>,
+    /// Details currently shown. Used to skip rebuilding parts that didn't change
+    rendered: Rc<RefCell<Option<Details>>>,
+    pub root: gtk::Box,
+}
+
+impl Card {
+    pub fn new(details: &Details, iface: Rc<IFaceRef>) -> Self {
+        let card = Self::build_widgets_tree(details);
+        card.setup_reply_handler(iface.clone());
+        card.update_from_details(details, iface);
+        card
+    }
+
+    /// Turns app icon into close button
+    pub fn connect_dismiss(&self, f: impl Fn() + 'static) {
+        let gesture_click = gtk::GestureClick::builder().build();
+        self.app_icon.add_controller(gesture_click.clone());
+        gesture_click.connect_released(move |gesture, _, _, _| {
+            f();
+
+            gesture.set_state(gtk::EventSequenceState::Claimed);
+        });
+
+        let event_conntroller_motion = gtk::EventControllerMotion::new();
+        self.app_icon
+            .add_controller(event_conntroller_motion.clone());
+
+        event_conntroller_motion.connect_enter(clone!(
+            #[strong(rename_to=app_icon)]
+            self.app_icon,
+            move |_, _, _| {
+                if app_icon.icon_name().is_some() || app_icon.file().is_some() {
+                    if let Some(icon_name) = app_icon.icon_name() {
+                        unsafe {
+                            app_icon.set_data("icon-name", icon_name);
+                        }
+                    }
+                    if let Some(file) = app_icon.file() {
+                        unsafe {
+                            app_icon.set_data("file", file);
+                        }
+                    }
+                }
+
+                app_icon.set_icon_name(Some(&CONFIG.lock().unwrap().window_close_icon.clone()));
+            }
+        ));
+
+        event_conntroller_motion.connect_leave(clone!(
+            #[strong(rename_to=app_icon)]
+            self.app_icon,
+            move |_| {
+                let icon_name = unsafe {
+                    app_icon
+                        .data::<glib::GString>("icon-name")
+                        .map(|v| v.as_ref().clone())
+                };
+                let file = unsafe {
+                    app_icon
+                        .data::<glib::GString>("file")
+                        .map(|v| v.as_ref().clone())
+                };
+
+                if let Some(icon_name) = icon_name {
+                    app_icon.set_icon_name(Some(&icon_name));
+                } else {
+                    app_icon.set_from_file(file);
+                }
+            }
+        ));
+    }
+
+    fn setup_reply_handler(&self, iface: Rc<IFaceRef>) {
+        let id = self.id;
+        self.reply_entry.connect_activate(clone!(
+            #[strong]
+            iface,
+            move |entry| {
+                if !entry.text().is_empty() {
+                    glib::spawn_future_local(clone!(
+                        #[strong]
+                        entry,
+                        #[strong]
+                        iface,
+                        async move {
+                            IFace::notification_replied(iface.signal_context(), id, &entry.text())
+                                .await
+                                .unwrap();
+                        }
+                    ));
+                } else {
+                    // TODO need to somehow notify user in the ui
+                    warn!("The entry cannot be empty!");
+                }
+            }
+        ));
+    }
+
+    /// Updates widgets in place.
+    /// Icon and action buttons are rebuilt only if they are changed
+    /// so frequent updates (e.g. progress) don't cause flickering
+    pub fn update_from_details(&self, details: &Details, iface: Rc<IFaceRef>) {
+        let previous = self.rendered.replace(Some(details.clone()));
+
+        self.update_labels(details);
+        if previous
+            .as_ref()
+            .is_none_or(|previous| previous.body != details.body)
+        {
+            self.update_body(details);
+        }
+        if previous
+            .as_ref()
+            .is_none_or(|previous| !Self::same_icon(previous, details))
+        {
+            self.update_icon(details);
+        }
+        self.update_urgency(details);
+        self.update_css_classes(details);
+        self.update_progress(details);
+
+        self.reply_entry
+            .set_visible(details.actions.iter().any(|a| a.key == "inline-reply"));
+
+        if previous.as_ref().is_none_or(|previous| {
+            previous.actions != details.actions
+                || previous.hints.action_icons != details.hints.action_icons
+        }) {
+            self.update_actions(details, iface);
+        }
+    }
+
+    fn same_icon(a: &Details, b: &Details) -> bool {
+        a.app_name == b.app_name
+            && a.app_icon == b.app_icon
+            && a.hints.desktop_entry == b.hints.desktop_entry
+            && a.hints.image_data == b.hints.image_data
+            && a.hints.image_path == b.hints.image_path
+            && a.hints.icon_data == b.hints.icon_data
+    }
+
+    fn update_progress(&self, details: &Details) {
+        match details.hints.value {
+            Some(value) => {
+                self.progress.set_fraction(value as f64 / 100.0);
+                self.progress.set_visible(true);
+            }
+            None => self.progress.set_visible(false),
+        }
+    }
+
+    /// Re-reads settings that can be changed by config reload
+    pub fn apply_config(&self, details: &Details) {
+        self.icon.set_pixel_size(CONFIG.lock().unwrap().icon_size);
+        self.update_icon(details);
+        self.update_body(details);
+    }
+
+    fn update_labels(&self, details: &Details) {
+        self.app_name
+            .set_label(details.app_name.as_deref().unwrap_or_default());
+        self.app_name
+            .set_visible(CONFIG.lock().unwrap().show_app_name);
+
+        self.summary.set_label(&details.summary);
+    }
+
+    /// Images are cut out of the body markup as Pango doesn't know `<img>`.
+    /// Tags are replaced by alt text if images are disabled or can't be loaded
+    fn update_body(&self, details: &Details) {
+        let config = CONFIG.lock().unwrap().body_images.clone();
+
+        while let Some(child) = self.body_images.first_child() {
+            self.body_images.remove(&child);
+        }
+
+        let body = details.body.as_deref().unwrap_or_default();
+        let mut textures = Vec::new();
+        let text = IMG_TAG.replace_all(body, |caps: &Captures| {
+            let (mut src, mut alt) = (None, None);
+            for attr in IMG_ATTR.captures_iter(&caps[1]) {
+                let value = attr.get(2).or(attr.get(3)).map(|m| m.as_str());
+                if attr[1].eq_ignore_ascii_case("src") {
+                    src = value;
+                } else {
+                    alt = value;
+                }
+            }
+
+            match src
+                .filter(|_| config.enabled)
+                .and_then(|src| Self::load_body_image(src, &config))
+            {
+                Some(texture) => {
+                    textures.push(texture);
+                    String::new()
+                }
+                // body is escaped on receive, alt must not add markup to it
+                None => glib::markup_escape_text(&alt.unwrap_or_default().replace("&amp;", "&"))
+                    .to_string(),
+            }
+        });
+
+        self.body.set_label(&text);
+        // body that consists of images only
+        self.body.set_visible(!text.trim().is_empty());
+
+        for texture in textures.iter() {
+            let (width, height) = (texture.width() as f64, texture.height() as f64);
+            let scale = (config.max_width as f64 / width)
+                .min(config.max_height as f64 / height)
+                .min(1.0);
+            let picture = gtk::Picture::builder()
+                .paintable(texture)
+                .can_shrink(true)
+                .halign(Align::Start)
+                .width_request((width * scale) as i32)
+                .height_request((height * scale) as i32)
+                .build();
+            self.body_images.append(&picture);
+        }
+        self.body_images.set_visible(!textures.is_empty());
+    }
+
+    /// Only local files are allowed
+    fn load_body_image(src: &str, config: &BodyImages) -> Option<gdk::Texture> {
+        // body is escaped on receive
+        let src = src.replace("&amp;", "&");
+        let path = if src.starts_with("file://") {
+            glib::filename_from_uri(&src).ok()?.0
+        } else if src.contains("://") {
+            warn!("Remote body image is refused: {}", src);
+            return None;
+        } else {
+            PathBuf::from(src)
+        };
+
+        if !path.is_absolute() {
+            warn!("Body image path is not absolute: {:?}", path);
+            return None;
+        }
+        let size = fs::metadata(&path)
+            .map_err(|e| warn!("Failed to read body image {:?}: {}", path, e))
+            .ok()?
+            .len();
+        if size > config.max_file_size {
+            warn!(
+                "Body image {:?} is too big: {} bytes, limit is {}",
+                path, size, config.max_file_size
+            );
+            return None;
+        }
+
+        let too_large = |width: i32, height: i32| {
+            let too_large = width > config.max_dimension || height > config.max_dimension;
+            if too_large {
+                warn!(
+                    "Body image {:?} is too large: {}x{}, limit is {}",
+                    path, width, height, config.max_dimension
+                );
+            }
+            too_large
+        };
+        // checked before decoding if format is known to gdk-pixbuf
+        if let Some((_, width, height)) = Pixbuf::file_info(&path) {
+            if too_large(width, height) {
+                return None;
+            }
+        }
+
+        gdk::Texture::from_file(&gio::File::for_path(&path))
+            .map_err(|e| warn!("Failed to load body image {:?}: {}", path, e))
+            .ok()
+            .filter(|texture| !too_large(texture.width(), texture.height()))
+    }
+
+    fn update_urgency(&self, details: &Details) {
+        for urgency in [Urgency::Low, Urgency::Normal, Urgency::Critical] {
+            self.root.remove_css_class(urgency.css_class());
+        }
+        self.root.add_css_class(details.hints.urgency.css_class());
+    }
+
+    /// Classes added by rules and category ones
+    fn update_css_classes(&self, details: &Details) {
+        for class in self.css_classes.borrow().iter() {
+            self.root.remove_css_class(class);
+        }
+
+        let mut classes = details.css_classes.clone();
+        if let Some(category) = &details.hints.category {
+            classes.extend(category.css_classes());
+        }
+        for class in classes.iter() {
+            self.root.add_css_class(class);
+        }
+        self.css_classes.replace(classes);
+    }
+
+    fn update_icon(&self, details: &Details) {
+        let app_info = Self::find_app_info(details);
+        self.set_app_icon(app_info);
+        self.set_image_icon(details);
+    }
+
+    fn find_app_info(details: &Details) -> Option<gio::DesktopAppInfo> {
+        details
+            .hints
+            .desktop_entry
+            .as_deref()
+            .and_then(|de| {
+                gio::DesktopAppInfo::new(de)
+                    .or_else(|| gio::DesktopAppInfo::new(&format!("{}.desktop", de)))
+            })
+            .or_else(|| {
+                details.app_name.as_deref().and_then(|an| {
+                    gio::DesktopAppInfo::new(an)
+                        .or_else(|| gio::DesktopAppInfo::new(&format!("{}.desktop", an)))
+                        .or_else(|| gio::DesktopAppInfo::new(&an.to_lowercase()))
+                        .or_else(|| {
+                            gio::DesktopAppInfo::new(&format!("{}.desktop", an.to_lowercase()))
+                        })
+                })
+            })
+    }
+
+    fn set_app_icon(&self, app_info: Option<gio::DesktopAppInfo>) {
+        if let Some(icon_name) =
+            app_info.and_then(|app| app.icon().and_then(|icon| icon.to_string()))
+        {
+            if PathBuf::from(icon_name.clone()).is_absolute() {
+                self.app_icon.set_from_file(Some(icon_name));
+            } else {
+                self.app_icon.set_icon_name(Some(&icon_name));
+            }
+        } else {
+            self.app_icon
+                .set_icon_name(Some(&CONFIG.lock().unwrap().window_close_icon));
+        }
+    }
+
+    fn set_image_icon(&self, details: &Details) {
+        let pixbuf: Option<Pixbuf> = details
+            .hints
+            .image_data
+            .clone()
+            .map(Pixbuf::from)
+            .or_else(|| {
+                details
+                    .hints
+                    .image_path
+                    .as_deref()
+                    .and_then(pixbuf::new_from_str)
+            })
+            .or_else(|| details.app_icon.as_deref().and_then(pixbuf::new_from_str))
+            .or_else(|| details.hints.icon_data.clone().map(Pixbuf::from))
+            .map(|pb| pixbuf::crop_square(&pb));
+
+        self.icon.set_visible(pixbuf.is_some());
+        if let Some(pb) = pixbuf {
+            self.icon.set_from_pixbuf(Some(&pb));
+        }
+    }
+
+    /// Action buttons in order they are shown
+    fn action_buttons(&self) -> Vec<gtk::Button> {
+        self.actions_box
+            .observe_children()
+            .into_iter()
+            .filter_map(|child| child.ok().and_downcast::<gtk::Button>())
+            .collect()
+    }
+
+    /// Clicks action button by its zero-based index. Returns `false` if there is no such
+    pub fn activate_action(&self, index: usize) -> bool {
+        let button = self.action_buttons().into_iter().nth(index);
+        if let Some(button) = &button {
+            button.emit_clicked();
+        }
+        button.is_some()
+    }
+
+    /// Moves focus to the next or previous action button cycling through them
+    pub fn focus_action(&self, forward: bool) {
+        let buttons = self.action_buttons();
+        if buttons.is_empty() {
+            return;
+        }
+
+        let len = buttons.len();
+        let index = match buttons.iter().position(|b| b.has_focus()) {
+            Some(index) if forward => (index + 1) % len,
+            Some(index) => (index + len - 1) % len,
+            None if forward => 0,
+            None => len - 1,
+        };
+        buttons[index].grab_focus();
+    }
+
+    fn update_actions(&self, details: &Details, iface: Rc<IFaceRef>) {
+        self.actions_box.set_visible(false);
+        self.actions_box
+            .observe_children()
+            .into_iter()
+            .filter_map(|child| child.ok().and_downcast::<gtk::Widget>())
+            .for_each(|child| self.actions_box.remove(&child));
+        for action in details.actions.iter().filter(|a| a.key != "default") {
+            self.actions_box
+                .append(&self.create_action_button(action, details, iface.clone()));
+            self.actions_box.set_visible(true);
+        }
+    }
+
+    fn create_action_button(
+        &self,
+        action: &Action,
+        details: &Details,
+        iface: Rc<IFaceRef>,
+    ) -> gtk::Button {
+        let details = details.clone();
+
+        let button = gtk::Button::builder().hexpand(true).build();
+        if !details.hints.action_icons {
+            button.set_label(&action.text);
+        } else {
+            let config = CONFIG.lock().unwrap().clone();
+            let redef = config.icons_alias.get(&action.key).unwrap_or(&action.key);
+            button.set_icon_name(redef);
+        }
+        button.set_tooltip_text(Some(&action.text));
+
+        if action.key == "inline-reply" {
+            button.connect_clicked(clone!(
+                #[strong(rename_to=s)]
+                self,
+                move |_| {
+                    if s.reply_entry.text().is_empty() {
+                        s.reply_revealer
+                            .set_reveal_child(!s.reply_revealer.reveals_child());
+                    } else {
+                        s.reply_entry.emit_activate();
+                    }
+                }
+            ));
+        } else {
+            button.connect_clicked(clone!(
+                #[strong]
+                iface,
+                #[strong]
+                action,
+                move |_| {
+                    hooks::run(Event::Action(action.key.clone()), &details);
+                    glib::spawn_future_local(clone!(
+                        #[strong]
+                        iface,
+                        #[strong]
+                        action,
+                        async move {
+                            if let Err(e) = IFace::action_invoked(
+                                iface.signal_context(),
+                                details.id,
+                                action.clone(),
+                            )
+                            .await
+                            {
+                                error!(
+                                    "Failed to invoke action: {} for window id: {}. Error: {:?}",
+                                    action.key, details.id, e
+                                );
+                            }
+                        }
+                    ));
+                }
+            ));
+        }
+
+        button
+    }
+
+    fn build_widgets_tree(details: &Details) -> Self {
+        let config = CONFIG.lock().unwrap().clone();
+
+        let app_name = gtk::Label::builder()
+            .name("app_name")
+            .justify(Justification::Left)
+            .halign(Align::Start)
+            .ellipsize(EllipsizeMode::End)
+            .sensitive(false)
+            .build();
+        let app_icon = gtk::Image::builder()
+            .name("app_icon")
+            .hexpand(true)
+            .halign(Align::End)
+            .visible(true)
+            .build();
+        let app_name_box = gtk::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .visible(config.show_app_name)
+            .build();
+        app_name_box.append(&app_name);
+        if config.show_app_name {
+            app_name_box.append(&app_icon);
+        }
+
+        let summary = gtk::Label::builder()
+            .name("summary")
+            .justify(Justification::Left)
+            .halign(Align::Start)
+            .ellipsize(EllipsizeMode::End)
+            .use_markup(true)
+            .build();
+
+        let icon = gtk::Image::builder()
+            .name("image")
+            .visible(false)
+            .pixel_size(config.icon_size)
+            .valign(Align::Center)
+            .halign(Align::End)
+            .build();
+
+        let summary_box = gtk::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .build();
+        summary_box.append(&summary);
+        if !config.show_app_name {
+            summary_box.append(&app_icon);
+        }
+
+        let body = gtk::Label::builder()
+            .name("body")
+            .justify(Justification::Left)
+            .valign(Align::Fill)
+            .halign(Align::Start)
+            .wrap(true)
+            .wrap_mode(pango::WrapMode::WordChar)
+            .use_markup(true)
+            .build();
+
+        let body_images = gtk::Box::builder()
+            .name("body-images")
+            .orientation(Orientation::Vertical)
+            .halign(Align::Start)
+            .spacing(5)
+            .visible(false)
+            .build();
+
+        let progress = gtk::ProgressBar::builder()
+            .name("progress")
+            .visible(false)
+            .build();
+
+        let reply_entry = gtk::Entry::builder()
+            .name("reply-entry")
+            .placeholder_text("Reply")
+            .build();
+        let reply_revealer = gtk::Revealer::builder()
+            .name("reply-revealer")
+            .reveal_child(false)
+            .child(&reply_entry)
+            .build();
+
+        let actions_box = gtk::Box::builder()
+            .name("actions")
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .build();
+
+        let content = gtk::Box::builder()
+            .name("content")
+            .orientation(Orientation::Vertical)
+            .valign(Align::Start)
+            .spacing(5)
+            .build();
+        content.append(&app_name_box);
+        content.append(&summary_box);
+        content.append(&body);
+        content.append(&body_images);
+        content.append(&progress);
+        content.append(&reply_revealer);
+
+        let body_box = gtk::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .build();
+        body_box.append(&icon);
+        body_box.append(&content);
+
+        let inner_box = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(5)
+            .margin_top(5)
+            .margin_start(5)
+            .margin_bottom(5)
+            .margin_end(5)
+            .build();
+        inner_box.append(&body_box);
+        inner_box.append(&actions_box);
+
+        let root = gtk::Box::builder()
+            .name("notification")
+            .orientation(Orientation::Vertical)
+            .build();
+        root.append(&inner_box);
+
+        Self {
+            id: details.id,
+            app_name,
+            app_icon,
+            icon,
+            summary,
+            body,
+            body_images,
+            progress,
+            reply_entry,
+            reply_revealer,
+            actions_box,
+            css_classes: Default::default(),
+            rendered: Default::default(),
+            root,
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use std::rc::Rc;
+
+use gtk::{
+    gdk,
+    glib::{self, clone},
+    prelude::*,
+    Align, Orientation, PolicyType,
+};
+use gtk_layer_shell::{KeyboardMode, LayerShell};
+#[allow(unused_imports)]
+use log::*;
+
+use crate::{
+    config::CONFIG,
+    dbus::{Action, Details, IFace, IFaceRef, Reason},
+    gui::window::Window,
+    hooks::{self, Event},
+    types::RuntimeData,
+};
+
+use super::{card::Card, utils::set_anchors};
+
+/// Sidebar panel with current and past notifications grouped by app
+#[derive(Clone)]
+pub struct Center {
+    list: gtk::Box,
+    placeholder: gtk::Label,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+    pub inner: gtk::Window,
+}
+
+impl Center {
+    pub fn build(
+        application: &gtk::Application,
+        iface: Rc<IFaceRef>,
+        runtime_data: RuntimeData,
+    ) -> Self {
+        let config = CONFIG.lock().unwrap().center.clone();
+
+        let inner = gtk::Window::builder()
+            .name("center")
+            .default_width(config.width)
+            .build();
+        inner.set_application(Some(application));
+
+        inner.init_layer_shell();
+        inner.set_namespace("rustyfications-center");
+        inner.set_keyboard_mode(KeyboardMode::OnDemand);
+        set_anchors(&inner, &config.edges);
+
+        let title = gtk::Label::builder()
+            .name("center-title")
+            .label("Notifications")
+            .halign(Align::Start)
+            .hexpand(true)
+            .build();
+        let clear_button = gtk::Button::builder()
+            .name("center-clear")
+            .label("Clear all")
+            .build();
+        let header = gtk::Box::builder()
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .build();
+        header.append(&title);
+        header.append(&clear_button);
+
+        let placeholder = gtk::Label::builder()
+            .name("center-placeholder")
+            .label("No notifications")
+            .sensitive(false)
+            .vexpand(true)
+            .build();
+
+        let list = gtk::Box::builder()
+            .name("center-list")
+            .orientation(Orientation::Vertical)
+            .spacing(10)
+            .build();
+
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(PolicyType::Never)
+            .vexpand(true)
+            .child(&list)
+            .build();
+
+        let main_box = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(5)
+            .margin_top(5)
+            .margin_start(5)
+            .margin_bottom(5)
+            .margin_end(5)
+            .build();
+        main_box.append(&header);
+        main_box.append(&placeholder);
+        main_box.append(&scrolled_window);
+
+        inner.set_child(Some(&main_box));
+
+        let center = Self {
+            list,
+            placeholder,
+            iface,
+            runtime_data,
+            inner,
+        };
+
+        clear_button.connect_clicked(clone!(
+            #[strong]
+            center,
+            move |_| center.dismiss_all()
+        ));
+
+        let event_controller_key = gtk::EventControllerKey::new();
+        center.inner.add_controller(event_controller_key.clone());
+        event_controller_key.connect_key_pressed(clone!(
+            #[strong]
+            center,
+            move |_, key, _, _| {
+                if key == gdk::Key::Escape {
+                    center.inner.set_visible(false);
+                    glib::Propagation::Stop
+                } else {
+                    glib::Propagation::Proceed
+                }
+            }
+        ));
+
+        center
+    }
+
+    pub fn toggle(&self) {
+        if self.inner.is_visible() {
+            info!("Hiding notification center");
+            self.inner.set_visible(false);
+        } else {
+            info!("Showing notification center");
+            self.inner.present();
+            self.refresh();
+        }
+    }
+
+    /// Rebuilds list of notifications. Does nothing while hidden
+    pub fn refresh(&self) {
+        if !self.inner.is_visible() {
+            return;
+        }
+
+        self.list
+            .observe_children()
+            .into_iter()
+            .filter_map(|child| child.ok().and_downcast::<gtk::Widget>())
+            .for_each(|child| self.list.remove(&child));
+
+        // newest first
+        let items: Vec<(Details, bool)> = {
+            let runtime_data = self.runtime_data.borrow();
+            let mut live: Vec<(Details, bool)> = runtime_data
+                .windows
+                .values()
+                .map(|w| (w.details(), true))
+                .collect();
+            live.sort_by_key(|(d, _)| std::cmp::Reverse(d.timestamp));
+
+            let past = runtime_data
+                .history
+                .entries()
+                .rev()
+                .filter(|e| !runtime_data.windows.contains_key(&e.id))
+                .map(|e| (e.to_details(), false));
+
+            live.into_iter().chain(past).collect()
+        };
+
+        self.placeholder.set_visible(items.is_empty());
+
+        let mut groups: Vec<(String, Vec<(Details, bool)>)> = Vec::new();
+        for (details, live) in items {
+            let app_name = details.app_name.clone().unwrap_or_default();
+            match groups.iter_mut().find(|(name, _)| *name == app_name) {
+                Some((_, group)) => group.push((details, live)),
+                None => groups.push((app_name, vec![(details, live)])),
+            }
+        }
+
+        for (app_name, group) in groups {
+            let group_box = gtk::Box::builder()
+                .orientation(Orientation::Vertical)
+                .spacing(5)
+                .css_classes(["center-group"])
+                .build();
+
+            let group_title = gtk::Label::builder()
+                .label(if app_name.is_empty() {
+                    "Other"
+                } else {
+                    &app_name
+                })
+                .halign(Align::Start)
+                .css_classes(["center-group-title"])
+                .build();
+            group_box.append(&group_title);
+
+            for (details, live) in group {
+                group_box.append(&self.build_card(&details, live).root);
+            }
+
+            self.list.append(&group_box);
+        }
+    }
+
+    /// Re-reads settings that can be changed by config reload
+    pub fn apply_config(&self) {
+        let config = CONFIG.lock().unwrap().center.clone();
+        self.inner.set_default_size(config.width, -1);
+        set_anchors(&self.inner, &config.edges);
+        self.refresh();
+    }
+
+    fn build_card(&self, details: &Details, live: bool) -> Card {
+        // client was already told that past notification is closed,
+        // so its actions can't be invoked anymore
+        let details = &if live {
+            details.clone()
+        } else {
+            Details {
+                actions: Vec::new(),
+                ..details.clone()
+            }
+        };
+
+        let card = Card::new(details, self.iface.clone());
+        if !live {
+            card.root.add_css_class("past");
+        }
+
+        let id = details.id;
+        card.connect_dismiss(clone!(
+            #[strong(rename_to=s)]
+            self,
+            move || s.dismiss(id)
+        ));
+
+        if details.actions.iter().any(|a| a.key == "default") {
+            let details = details.clone();
+            let resident = details.hints.resident;
+            let gesture_click = gtk::GestureClick::builder().button(1).build();
+            card.root.add_controller(gesture_click.clone());
+            gesture_click.connect_released(clone!(
+                #[strong(rename_to=s)]
+                self,
+                move |gesture, _, _, _| {
+                    hooks::run(Event::Action(Action::default().key), &details);
+                    glib::spawn_future_local(clone!(
+                        #[strong]
+                        s,
+                        async move {
+                            if let Err(e) = IFace::action_invoked(
+                                s.iface.signal_context(),
+                                id,
+                                Action::default(),
+                            )
+                            .await
+                            {
+                                error!(
+                                    "Failed to invoke default action for id: {}. Error: {:?}",
+                                    id, e
+                                );
+                            }
+
+                            if !resident {
+                                s.dismiss(id);
+                            }
+                        }
+                    ));
+
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                }
+            ));
+        }
+
+        card
+    }
+
+    /// Closes notification if it is still on screen and removes it from history
+    pub fn dismiss(&self, id: u32) {
+        debug!("Dismissing notification with id: {} from center", id);
+
+        let window = self.runtime_data.borrow().windows.get(&id).cloned();
+        match window {
+            // center will be refreshed by close hook
+            Some(window) => {
+                window.set_skip_history(true);
+                window.close(Reason::Dismissed);
+            }
+            None => {
+                self.runtime_data.borrow_mut().history.remove(id);
+                self.refresh();
+            }
+        }
+    }
+
+    pub fn dismiss_all(&self) {
+        info!("Dismissing all notifications from center");
+
+        let windows: Vec<Window> = self
+            .runtime_data
+            .borrow()
+            .windows
+            .values()
+            .cloned()
+            .collect();
+        for window in windows {
+            window.set_skip_history(true);
+            window.close(Reason::Dismissed);
+        }
+
+        self.runtime_data.borrow_mut().history.clear();
+        self.refresh();
+    }
+}
+
+/// Refresh center if it is exists
+pub fn refresh(runtime_data: &RuntimeData) {
+    let center = runtime_data.borrow().center.clone();
+    if let Some(center) = center {
+        center.refresh();
+    }
+}
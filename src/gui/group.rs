@@ -0,0 +1,374 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{
+    glib::{self, clone},
+    pango::EllipsizeMode,
+    prelude::*,
+    Align, Justification, Orientation,
+};
+use gtk_layer_shell::{KeyboardMode, LayerShell};
+#[allow(unused_imports)]
+use log::*;
+
+use crate::{
+    config::CONFIG,
+    dbus::{IFaceRef, Reason, Urgency},
+    types::RuntimeData,
+    utils::close_hook,
+};
+
+use super::{
+    stack::Stack,
+    utils::{init_layer_shell, margins_update, margins_update_on_map, set_anchors},
+    window::Window,
+};
+
+/// Popup that collapses several notifications with the same group key.
+/// Members are still [Window]s, but only their cards are shown here
+#[derive(Clone)]
+pub struct Group {
+    pub key: String,
+    members: Rc<RefCell<Vec<Window>>>,
+    count: gtk::Label,
+    summary: gtk::Label,
+    expand_button: gtk::Button,
+    revealer: gtk::Revealer,
+    list: gtk::Box,
+    root: gtk::Box,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+    pub inner: gtk::Window,
+}
+
+impl Group {
+    pub fn build(
+        key: String,
+        application: &gtk::Application,
+        iface: Rc<IFaceRef>,
+        runtime_data: RuntimeData,
+    ) -> Self {
+        info!("Building group for key: {}", key);
+        let group = Self::build_widgets_tree(key, iface, runtime_data);
+        init_layer_shell(&group.inner);
+        group.inner.set_application(Some(application));
+
+        group.expand_button.connect_clicked(clone!(
+            #[strong(rename_to=s)]
+            group,
+            move |_| s.toggle_expanded()
+        ));
+
+        group.revealer.connect_child_revealed_notify(clone!(
+            #[strong(rename_to=s)]
+            group,
+            move |_| margins_update(s.runtime_data.clone())
+        ));
+
+        let event_controller_motion = gtk::EventControllerMotion::new();
+        group.root.add_controller(event_controller_motion.clone());
+        event_controller_motion.connect_enter(clone!(
+            #[strong(rename_to=s)]
+            group,
+            move |_, _, _| s.set_hover(true)
+        ));
+        event_controller_motion.connect_leave(clone!(
+            #[strong(rename_to=s)]
+            group,
+            move |_| s.set_hover(false)
+        ));
+
+        group
+    }
+
+    /// The highest urgency among members
+    pub fn urgency(&self) -> Urgency {
+        self.members
+            .borrow()
+            .iter()
+            .map(|w| w.details().hints.urgency)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Latest member id. Used to place group in the stack
+    pub fn latest_id(&self) -> u32 {
+        self.members
+            .borrow()
+            .iter()
+            .map(|w| w.id)
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub fn add(&self, window: &Window) {
+        debug!("Window id: {} joins group: {}", window.id, self.key);
+
+        if CONFIG.lock().unwrap().new_on_top {
+            self.list.prepend(window.widget());
+        } else {
+            self.list.append(window.widget());
+        }
+
+        self.members.borrow_mut().push(window.clone());
+        self.refresh();
+    }
+
+    /// Removes closed member. Group itself is closed with the last one
+    pub fn remove(&self, window: &Window, reason: Reason) {
+        let removed = {
+            let mut members = self.members.borrow_mut();
+            let len = members.len();
+            members.retain(|w| w.id != window.id);
+            len != members.len()
+        };
+        if !removed {
+            return;
+        }
+
+        debug!("Window id: {} leaves group: {}", window.id, self.key);
+        self.list.remove(window.widget());
+
+        if self.members.borrow().is_empty() {
+            info!("Closing group: {}", self.key);
+            self.runtime_data.borrow_mut().groups.remove(&self.key);
+            if let Some(stack) = self.stack() {
+                stack.remove(&self.root);
+            }
+            self.inner.close();
+        } else {
+            self.refresh();
+        }
+
+        glib::spawn_future_local(close_hook(
+            window.id,
+            reason,
+            self.iface.clone(),
+            self.runtime_data.clone(),
+        ));
+    }
+
+    /// Closes every member
+    pub fn dismiss(&self) {
+        let members = self.members.borrow().clone();
+        for window in members.iter() {
+            window.close(Reason::Dismissed);
+        }
+    }
+
+    /// Updates header with members count and latest summary
+    pub fn refresh(&self) {
+        let members = self.members.borrow();
+        let Some(latest) = members.iter().max_by_key(|w| w.id) else {
+            return;
+        };
+        let details = latest.details();
+
+        self.count.set_label(&match &details.app_name {
+            Some(app_name) => format!("{} notifications from {}", members.len(), app_name),
+            None => format!("{} notifications", members.len()),
+        });
+        self.summary.set_label(&details.summary);
+
+        // keep place in the stack by latest member once group is shown there
+        if let Some(stack) = self.stack().filter(|_| self.inner.child().is_none()) {
+            stack.insert(latest.id, &self.root);
+        }
+    }
+
+    /// Re-reads settings that can be changed by config reload
+    pub fn apply_config(&self) {
+        let config = CONFIG.lock().unwrap().clone();
+        self.inner
+            .set_default_size(config.window_size.0, config.window_size.1);
+        set_anchors(&self.inner, &config.edges);
+    }
+
+    pub fn present(&self) {
+        match self.stack() {
+            Some(stack) => {
+                self.inner.set_child(None::<&gtk::Widget>);
+                stack.insert(self.latest_id(), &self.root);
+            }
+            None => {
+                self.inner.present();
+                margins_update_on_map(&self.inner, self.runtime_data.clone());
+            }
+        }
+    }
+
+    fn stack(&self) -> Option<Stack> {
+        self.runtime_data.borrow().stack.clone()
+    }
+
+    /// Layer surface the group is shown on
+    pub fn surface(&self) -> gtk::Window {
+        self.stack()
+            .map_or_else(|| self.inner.clone(), |stack| stack.inner)
+    }
+
+    fn toggle_expanded(&self) {
+        let expanded = !self.revealer.reveals_child();
+        self.revealer.set_reveal_child(expanded);
+        self.expand_button.set_icon_name(if expanded {
+            "pan-up-symbolic"
+        } else {
+            "pan-down-symbolic"
+        });
+    }
+
+    /// Same as [Window::toggle_hover] but for all members at once
+    fn set_hover(&self, hover: bool) {
+        let members = self.members.borrow().clone();
+        if hover {
+            self.surface().set_keyboard_mode(KeyboardMode::OnDemand);
+            self.root.add_css_class("hover");
+            members.iter().for_each(Window::stop_timeout);
+        } else {
+            self.surface().set_keyboard_mode(KeyboardMode::None);
+            self.root.remove_css_class("hover");
+            members.iter().for_each(Window::start_timeout);
+        }
+    }
+
+    fn build_widgets_tree(key: String, iface: Rc<IFaceRef>, runtime_data: RuntimeData) -> Self {
+        let config = CONFIG.lock().unwrap().clone();
+
+        let inner = gtk::Window::builder()
+            .default_width(config.window_size.0)
+            .default_height(config.window_size.1)
+            .build();
+        inner.add_css_class("popup");
+
+        let count = gtk::Label::builder()
+            .name("group-count")
+            .justify(Justification::Left)
+            .halign(Align::Start)
+            .ellipsize(EllipsizeMode::End)
+            .sensitive(false)
+            .build();
+        let summary = gtk::Label::builder()
+            .name("group-summary")
+            .justify(Justification::Left)
+            .halign(Align::Start)
+            .ellipsize(EllipsizeMode::End)
+            .use_markup(true)
+            .build();
+
+        let labels = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .hexpand(true)
+            .spacing(5)
+            .build();
+        labels.append(&count);
+        labels.append(&summary);
+
+        let expand_button = gtk::Button::builder()
+            .name("group-expand")
+            .icon_name("pan-down-symbolic")
+            .valign(Align::Center)
+            .build();
+        let dismiss_button = gtk::Button::builder()
+            .name("group-dismiss")
+            .icon_name(&config.window_close_icon)
+            .valign(Align::Center)
+            .build();
+
+        let header = gtk::Box::builder()
+            .name("group-header")
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .margin_top(5)
+            .margin_start(5)
+            .margin_bottom(5)
+            .margin_end(5)
+            .build();
+        header.append(&labels);
+        header.append(&expand_button);
+        header.append(&dismiss_button);
+
+        let list = gtk::Box::builder()
+            .name("group-members")
+            .orientation(Orientation::Vertical)
+            .spacing(5)
+            .margin_start(5)
+            .margin_bottom(5)
+            .margin_end(5)
+            .build();
+        let revealer = gtk::Revealer::builder()
+            .reveal_child(false)
+            .child(&list)
+            .build();
+
+        let root = gtk::Box::builder()
+            .name("group")
+            .orientation(Orientation::Vertical)
+            .build();
+        root.append(&header);
+        root.append(&revealer);
+        inner.set_child(Some(&root));
+
+        let group = Self {
+            key,
+            members: Default::default(),
+            count,
+            summary,
+            expand_button,
+            revealer,
+            list,
+            root,
+            iface,
+            runtime_data,
+            inner,
+        };
+
+        dismiss_button.connect_clicked(clone!(
+            #[strong(rename_to=s)]
+            group,
+            move |_| s.dismiss()
+        ));
+
+        group
+    }
+}
+
+/// Puts new window into a group if it has group key.
+/// Group is formed when there are enough notifications with the same key on screen.
+/// Returns `true` if window is grouped and should not be presented itself
+pub fn place(
+    window: &Window,
+    application: &gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) -> bool {
+    let grouping = CONFIG.lock().unwrap().grouping.clone();
+    let Some(key) = grouping.key(&window.details()) else {
+        return false;
+    };
+
+    let group = runtime_data.borrow().groups.get(&key).cloned();
+    if let Some(group) = group {
+        window.join(&group);
+        return true;
+    }
+
+    let siblings: Vec<Window> = runtime_data
+        .borrow()
+        .windows
+        .values()
+        .filter(|w| w.id != window.id && !w.is_grouped() && w.position().is_none())
+        .filter(|w| grouping.key(&w.details()).as_ref() == Some(&key))
+        .cloned()
+        .collect();
+    if siblings.len() + 1 < grouping.min_size {
+        return false;
+    }
+
+    let group = Group::build(key.clone(), application, iface, runtime_data.clone());
+    runtime_data.borrow_mut().groups.insert(key, group.clone());
+    for sibling in siblings.iter().chain([window]) {
+        sibling.join(&group);
+    }
+    group.present();
+
+    true
+}
@@ -1,3 +1,8 @@
+pub mod card;
+pub mod center;
+pub mod group;
+pub mod overflow;
+pub mod stack;
 pub mod utils;
 pub mod window;
 
@@ -0,0 +1,280 @@
+use std::rc::Rc;
+
+use gtk::{glib::clone, prelude::*, Align, Orientation};
+#[allow(unused_imports)]
+use log::*;
+
+use crate::{
+    config::CONFIG,
+    dbus::{Details, IFaceRef, Reason},
+    gui::center,
+    new_notification,
+    types::RuntimeData,
+    utils::close_queued,
+};
+
+use super::{
+    stack::OVERFLOW_KEY,
+    utils::{init_layer_shell, layer_update, margins_update, margins_update_on_map, set_anchors},
+};
+
+/// Number of popups on screen. Group counts as one
+fn visible_count(runtime_data: &RuntimeData) -> usize {
+    let runtime_data = runtime_data.borrow();
+    runtime_data
+        .windows
+        .values()
+        .filter(|w| !w.is_grouped())
+        .count()
+        + runtime_data.groups.len()
+}
+
+/// Whether notification should wait in overflow queue instead of being shown
+pub fn should_queue(details: &Details, runtime_data: &RuntimeData) -> bool {
+    let config = CONFIG.lock().unwrap();
+    let Some(max_visible) = config.max_visible else {
+        return false;
+    };
+
+    // joining existing group doesn't take more space
+    let joins_group = config
+        .grouping
+        .key(details)
+        .is_some_and(|key| runtime_data.borrow().groups.contains_key(&key));
+
+    !joins_group && visible_count(runtime_data) >= max_visible
+}
+
+/// Compact "+N more" popup placed after the stack.
+/// Queued notifications are shown (and their timeouts started) only when they are promoted
+#[derive(Clone)]
+pub struct Overflow {
+    label: gtk::Label,
+    root: gtk::Box,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+    pub inner: gtk::Window,
+}
+
+impl Overflow {
+    pub fn build(
+        application: &gtk::Application,
+        iface: Rc<IFaceRef>,
+        runtime_data: RuntimeData,
+    ) -> Self {
+        let inner = gtk::Window::builder()
+            .default_width(CONFIG.lock().unwrap().window_size.0)
+            .build();
+        inner.add_css_class("popup");
+        inner.set_application(Some(application));
+        init_layer_shell(&inner);
+
+        let label = gtk::Label::builder()
+            .name("overflow-count")
+            .halign(Align::Start)
+            .hexpand(true)
+            .build();
+        let expand_button = gtk::Button::builder()
+            .name("overflow-expand")
+            .label("Show all")
+            .build();
+        let clear_button = gtk::Button::builder()
+            .name("overflow-clear")
+            .label("Clear")
+            .build();
+
+        let root = gtk::Box::builder()
+            .name("overflow")
+            .orientation(Orientation::Horizontal)
+            .spacing(5)
+            .build();
+        root.append(&label);
+        root.append(&expand_button);
+        root.append(&clear_button);
+        inner.set_child(Some(&root));
+
+        let overflow = Self {
+            label,
+            root,
+            application: application.clone(),
+            iface,
+            runtime_data,
+            inner,
+        };
+
+        expand_button.connect_clicked(clone!(
+            #[strong(rename_to=s)]
+            overflow,
+            move |_| s.expand()
+        ));
+        clear_button.connect_clicked(clone!(
+            #[strong(rename_to=s)]
+            overflow,
+            move |_| s.clear()
+        ));
+
+        overflow
+    }
+
+    fn queue(&self, details: Details) {
+        info!(
+            "Too many popups on screen. Notification with id: {} is queued",
+            details.id
+        );
+        self.runtime_data.borrow_mut().overflow_queue.push(details);
+        self.refresh();
+    }
+
+    /// Removes queued notification. Returns `false` if there is no such
+    fn remove(&self, id: u32, reason: Reason) -> bool {
+        let details = {
+            let mut runtime_data = self.runtime_data.borrow_mut();
+            let index = runtime_data.overflow_queue.iter().position(|d| d.id == id);
+            index.map(|index| runtime_data.overflow_queue.remove(index))
+        };
+        let Some(details) = details else {
+            return false;
+        };
+
+        self.closed(&details, reason);
+        self.refresh();
+        true
+    }
+
+    /// Shows queued notifications while there is free space.
+    /// Oldest ones go first so the stack keeps order set by `new_on_top`
+    fn promote(&self) {
+        let max_visible = CONFIG.lock().unwrap().max_visible;
+
+        loop {
+            if max_visible.is_some_and(|max| visible_count(&self.runtime_data) >= max) {
+                break;
+            }
+
+            let details = {
+                let mut runtime_data = self.runtime_data.borrow_mut();
+                if runtime_data.overflow_queue.is_empty() {
+                    break;
+                }
+                runtime_data.overflow_queue.remove(0)
+            };
+            debug!("Promoting notification with id: {}", details.id);
+            self.show(details);
+        }
+
+        self.refresh();
+    }
+
+    /// Shows all queued notifications ignoring the limit
+    fn expand(&self) {
+        let queued = std::mem::take(&mut self.runtime_data.borrow_mut().overflow_queue);
+        info!("Showing {} queued notification(s)", queued.len());
+        for details in queued {
+            self.show(details);
+        }
+        self.refresh();
+    }
+
+    /// Dismisses all queued notifications
+    fn clear(&self) {
+        let queued = std::mem::take(&mut self.runtime_data.borrow_mut().overflow_queue);
+        info!("Dismissing {} queued notification(s)", queued.len());
+        for details in queued.iter() {
+            self.closed(details, Reason::Dismissed);
+        }
+        self.refresh();
+        center::refresh(&self.runtime_data);
+    }
+
+    fn show(&self, details: Details) {
+        new_notification(
+            details,
+            self.application.clone(),
+            self.iface.clone(),
+            self.runtime_data.clone(),
+        );
+    }
+
+    fn closed(&self, details: &Details, reason: Reason) {
+        close_queued(details, reason, self.iface.clone(), &self.runtime_data);
+    }
+
+    /// Re-reads settings that can be changed by config reload
+    pub fn apply_config(&self) {
+        let config = CONFIG.lock().unwrap().clone();
+        self.inner.set_default_size(config.window_size.0, -1);
+        set_anchors(&self.inner, &config.edges);
+        // limit could be raised
+        self.promote();
+    }
+
+    /// Updates counter and shows or hides the popup
+    fn refresh(&self) {
+        let count = self.runtime_data.borrow().overflow_queue.len();
+        self.label.set_label(&format!("+{} more", count));
+
+        let stack = self.runtime_data.borrow().stack.clone();
+        if let Some(stack) = stack {
+            if count == 0 {
+                stack.remove(&self.root);
+            } else {
+                self.inner.set_child(None::<&gtk::Widget>);
+                stack.insert(OVERFLOW_KEY, &self.root);
+            }
+        } else if count == 0 {
+            self.inner.set_visible(false);
+            margins_update(self.runtime_data.clone());
+        } else if !self.inner.is_visible() {
+            self.inner.present();
+            margins_update_on_map(&self.inner, self.runtime_data.clone());
+        } else {
+            margins_update(self.runtime_data.clone());
+        }
+
+        // presented surface could be hidden by fullscreen policy
+        layer_update(&self.runtime_data);
+    }
+}
+
+fn get(runtime_data: &RuntimeData) -> Option<Overflow> {
+    runtime_data.borrow().overflow.clone()
+}
+
+pub fn queue(details: Details, runtime_data: &RuntimeData) {
+    if let Some(overflow) = get(runtime_data) {
+        overflow.queue(details);
+    }
+}
+
+pub fn is_queued(id: u32, runtime_data: &RuntimeData) -> bool {
+    runtime_data
+        .borrow()
+        .overflow_queue
+        .iter()
+        .any(|d| d.id == id)
+}
+
+/// Replaces queued notification in place
+pub fn replace(details: Details, runtime_data: &RuntimeData) {
+    let mut runtime_data = runtime_data.borrow_mut();
+    if let Some(queued) = runtime_data
+        .overflow_queue
+        .iter_mut()
+        .find(|d| d.id == details.id)
+    {
+        *queued = details;
+    }
+}
+
+/// Removes queued notification. Returns `false` if there is no such
+pub fn remove(id: u32, reason: Reason, runtime_data: &RuntimeData) -> bool {
+    get(runtime_data).is_some_and(|overflow| overflow.remove(id, reason))
+}
+
+/// Shows queued notifications while there is free space
+pub fn promote(runtime_data: &RuntimeData) {
+    if let Some(overflow) = get(runtime_data) {
+        overflow.promote();
+    }
+}
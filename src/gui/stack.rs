@@ -0,0 +1,151 @@
+use std::{cell::RefCell, rc::Rc};
+
+use gtk::{glib, prelude::*, Orientation};
+use gtk_layer_shell::{KeyboardMode, LayerShell};
+#[allow(unused_imports)]
+use log::*;
+
+use crate::{
+    config::{edge::Edge, CONFIG},
+    dbus::{IFaceRef, Reason},
+    types::RuntimeData,
+    utils::close_hook,
+};
+
+use super::utils::{init_layer_shell, set_anchors, stack_indent, stack_spacing};
+
+/// Sort key of the "+N more" popup. It is always at the end of the stack
+pub const OVERFLOW_KEY: u32 = u32::MAX;
+
+/// Single layer surface holding all popups.
+/// Stacking is done by the box layout instead of layer-shell margins
+#[derive(Clone)]
+pub struct Stack {
+    list: gtk::Box,
+    /// Children with their sort keys (notification id)
+    items: Rc<RefCell<Vec<(u32, gtk::Widget)>>>,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+    pub inner: gtk::Window,
+}
+
+impl Stack {
+    pub fn build(
+        application: &gtk::Application,
+        iface: Rc<IFaceRef>,
+        runtime_data: RuntimeData,
+    ) -> Self {
+        let inner = gtk::Window::builder()
+            .name("stack")
+            .default_width(CONFIG.lock().unwrap().window_size.0)
+            .build();
+        inner.add_css_class("popup");
+        inner.set_application(Some(application));
+        init_layer_shell(&inner);
+        inner.set_namespace("rustyfications");
+        inner.set_keyboard_mode(KeyboardMode::None);
+
+        let list = gtk::Box::builder()
+            .name("stack-list")
+            .orientation(Orientation::Vertical)
+            .build();
+        inner.set_child(Some(&list));
+
+        let stack = Self {
+            list,
+            items: Default::default(),
+            iface,
+            runtime_data,
+            inner,
+        };
+        stack.set_margins();
+        stack
+    }
+
+    /// Popups are placed exactly as in per-window mode,
+    /// so switching modes doesn't shift them
+    fn set_margins(&self) {
+        let edges = CONFIG.lock().unwrap().edges.clone();
+        set_anchors(&self.inner, &edges);
+        for edge in [Edge::Top, Edge::Bottom] {
+            if edges.contains_key(&edge) {
+                self.inner.set_margin(edge.into(), stack_indent(&edges));
+            }
+        }
+        self.list.set_spacing(stack_spacing(&edges));
+    }
+
+    /// Adds widget or moves it if it is already here
+    pub fn insert(&self, key: u32, widget: &impl IsA<gtk::Widget>) {
+        let widget = widget.as_ref();
+        {
+            let mut items = self.items.borrow_mut();
+            match items.iter_mut().find(|(_, w)| w == widget) {
+                Some(item) => item.0 = key,
+                None => {
+                    self.list.append(widget);
+                    items.push((key, widget.clone()));
+                }
+            }
+        }
+
+        self.reorder();
+        if !self.inner.is_visible() {
+            self.inner.present();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+
+    pub fn remove(&self, widget: &impl IsA<gtk::Widget>) {
+        let widget = widget.as_ref();
+        self.items.borrow_mut().retain(|(_, w)| w != widget);
+        if widget.parent().as_ref() == Some(self.list.upcast_ref()) {
+            self.list.remove(widget);
+        }
+
+        if self.is_empty() {
+            self.inner.set_visible(false);
+        }
+    }
+
+    /// Runs close hook for notification which card was shown here.
+    /// Own window is never realized in this mode, so there is no `unrealize` to rely on
+    pub fn closed(&self, id: u32, reason: Reason) {
+        glib::spawn_future_local(close_hook(
+            id,
+            reason,
+            self.iface.clone(),
+            self.runtime_data.clone(),
+        ));
+    }
+
+    /// Orders children by key following `new_on_top`. Overflow stays last anyway
+    fn reorder(&self) {
+        let mut items = self.items.borrow().clone();
+        items.sort_by_key(|(key, _)| *key);
+        if CONFIG.lock().unwrap().new_on_top {
+            items.reverse();
+        }
+        if let Some(index) = items.iter().position(|(key, _)| *key == OVERFLOW_KEY) {
+            let overflow = items.remove(index);
+            items.push(overflow);
+        }
+
+        let mut previous: Option<gtk::Widget> = None;
+        for (_, widget) in items {
+            self.list.reorder_child_after(&widget, previous.as_ref());
+            previous = Some(widget);
+        }
+    }
+
+    /// Re-reads settings that can be changed by config reload
+    pub fn apply_config(&self) {
+        let config = CONFIG.lock().unwrap().clone();
+        self.inner.set_default_size(config.window_size.0, -1);
+        self.set_margins();
+        self.reorder();
+    }
+}
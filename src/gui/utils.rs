@@ -1,21 +1,148 @@
-use gtk::prelude::WidgetExt;
+use std::{collections::HashMap, ptr, time::Duration};
+
+use gtk::{
+    glib::{self, translate::ToGlibPtr},
+    prelude::{Cast, GtkWindowExt, WidgetExt},
+};
 use gtk_layer_shell::{Edge, LayerShell};
 
 use crate::{
     config::{
         edge::{Edge as ConfigEdge, EdgeInfo},
+        output::Output,
         CONFIG,
     },
+    dbus::Urgency,
+    fullscreen,
     types::RuntimeData,
 };
 
-use super::window::Window;
+use super::{group::Group, stack::OVERFLOW_KEY, window::Window};
 
 pub fn init_layer_shell(window: &impl LayerShell) {
     window.init_layer_shell();
 
-    let edges = CONFIG.lock().unwrap().edges.clone();
+    let config = CONFIG.lock().unwrap().clone();
+    set_anchors(window, &config.edges);
+    set_output(window, &config.output);
+}
+
+/// Does nothing if window is already there, as changing output remaps it
+pub fn set_output(window: &impl LayerShell, output: &Output) {
+    let monitor = output.monitor();
+    if window.monitor() == monitor {
+        return;
+    }
+
+    match monitor {
+        Some(monitor) => window.set_monitor(&monitor),
+        // bindings don't allow to unset it
+        None => unsafe {
+            gtk_layer_shell_sys::gtk_layer_set_monitor(
+                window.upcast_ref::<gtk::Window>().to_glib_none().0,
+                ptr::null_mut(),
+            );
+        },
+    }
+}
+
+/// Sets layer of every popup surface by urgency of notifications shown on it
+pub fn layer_update(runtime_data: &RuntimeData) {
+    let config = CONFIG.lock().unwrap().clone();
+    let fullscreen = fullscreen::is_active(runtime_data);
+
+    let surfaces = shown_surfaces(runtime_data);
+    for (window, urgency) in surfaces.iter() {
+        let layer = config.layer(*urgency, fullscreen).into();
+        if window.layer() != layer {
+            window.set_layer(layer);
+        }
+    }
+
+    // hidden ones that are gone are forgotten
+    let hidden = std::mem::take(&mut runtime_data.borrow_mut().fullscreen_hidden);
+    let mut still_hidden = Vec::new();
+    let mut changed = false;
+    for (window, urgency) in surfaces {
+        if fullscreen::hides(urgency, fullscreen) {
+            if window.is_visible() {
+                window.set_visible(false);
+                changed = true;
+                still_hidden.push(window);
+            } else if hidden.contains(&window) {
+                still_hidden.push(window);
+            }
+        } else if hidden.contains(&window) {
+            window.present();
+            margins_update_on_map(&window, runtime_data.clone());
+        }
+    }
+    runtime_data.borrow_mut().fullscreen_hidden = still_hidden;
+    if changed {
+        margins_update(runtime_data.clone());
+    }
+}
+
+/// Layer surfaces that should be on screen with urgency they are shown for
+fn shown_surfaces(runtime_data: &RuntimeData) -> Vec<(gtk::Window, Urgency)> {
+    let runtime_data = runtime_data.borrow();
+    let mut surfaces: Vec<(gtk::Window, Urgency)> = runtime_data
+        .windows
+        .values()
+        .filter(|w| !w.is_embedded())
+        .map(|w| (w.inner.clone(), w.details().hints.urgency))
+        .collect();
+
+    match &runtime_data.stack {
+        // everything else is on one surface
+        Some(stack) if !stack.is_empty() => {
+            let urgency = runtime_data
+                .windows
+                .values()
+                .map(|w| w.details().hints.urgency)
+                .max()
+                .unwrap_or_default();
+            surfaces.push((stack.inner.clone(), urgency));
+        }
+        Some(_) => {}
+        None => {
+            surfaces.extend(
+                runtime_data
+                    .groups
+                    .values()
+                    .map(|g| (g.inner.clone(), g.urgency())),
+            );
+            if let Some(overflow) = runtime_data
+                .overflow
+                .as_ref()
+                .filter(|_| !runtime_data.overflow_queue.is_empty())
+            {
+                surfaces.push((overflow.inner.clone(), Urgency::Normal));
+            }
+        }
+    }
+    surfaces
+}
+
+/// Moves all popups to configured output.
+/// Called on config reload and when monitors are plugged or unplugged
+pub fn output_update(runtime_data: &RuntimeData) {
+    let output = CONFIG.lock().unwrap().output.clone();
+
+    let runtime_data = runtime_data.borrow();
+    let surfaces = runtime_data
+        .windows
+        .values()
+        .map(|w| &w.inner)
+        .chain(runtime_data.groups.values().map(|g| &g.inner))
+        .chain(runtime_data.overflow.iter().map(|o| &o.inner))
+        .chain(runtime_data.stack.iter().map(|s| &s.inner));
+    for window in surfaces {
+        set_output(window, &output);
+    }
+}
 
+pub fn set_anchors(window: &impl LayerShell, edges: &HashMap<ConfigEdge, EdgeInfo>) {
     for edge in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom] {
         window.set_anchor(edge, edges.contains_key(&ConfigEdge::from(edge)));
     }
@@ -25,38 +152,130 @@ pub fn init_layer_shell(window: &impl LayerShell) {
     });
 }
 
+/// Updates margins as soon as window is mapped and its height is known
+pub fn margins_update_on_map(window: &gtk::Window, runtime_data: RuntimeData) {
+    let window = window.clone();
+    glib::timeout_add_local(Duration::from_millis(50), move || {
+        if window.is_mapped() {
+            margins_update(runtime_data.clone());
+            glib::ControlFlow::Break
+        } else if !window.is_visible() {
+            // closed or hidden before it was mapped
+            glib::ControlFlow::Break
+        } else {
+            glib::ControlFlow::Continue
+        }
+    });
+}
+
 pub fn margins_update(runtime_data: RuntimeData) {
     let edges = CONFIG.lock().unwrap().edges.clone();
 
     let runtime_data = runtime_data.borrow();
-    let windows_iter: Box<dyn Iterator<Item = (&u32, &Window)>> =
-        if CONFIG.lock().unwrap().new_on_top {
-            Box::new(runtime_data.windows.iter().rev())
-        } else {
-            Box::new(runtime_data.windows.iter())
-        };
+    // height could be changed, so they are clamped again
+    for window in runtime_data.windows.values() {
+        window.place_at_position();
+    }
+    // single surface is stacked by box layout
+    if runtime_data.stack.is_some() {
+        return;
+    }
 
-    let mut top_bottom_indent = edges
-        .get(&ConfigEdge::Top)
-        .or_else(|| edges.get(&ConfigEdge::Bottom))
-        .map_or(0, |edge_info| edge_info.padding);
+    // groups are placed in the stack by their latest member
+    let mut surfaces: Vec<(u32, &gtk::Window)> = runtime_data
+        .windows
+        .values()
+        .filter(|w| !w.is_grouped() && w.position().is_none())
+        .map(|w| (w.id, &w.inner))
+        .chain(
+            runtime_data
+                .groups
+                .values()
+                .map(|g| (g.latest_id(), &g.inner)),
+        )
+        // unmapped by fullscreen policy
+        .filter(|(_, w)| w.is_visible())
+        .collect();
+    surfaces.sort_by_key(|(id, _)| *id);
+    if CONFIG.lock().unwrap().new_on_top {
+        surfaces.reverse();
+    }
+    // "+N more" is always at the end of the stack
+    if let Some(overflow) = runtime_data
+        .overflow
+        .as_ref()
+        .filter(|o| o.inner.is_visible())
+    {
+        surfaces.push((OVERFLOW_KEY, &overflow.inner));
+    }
+
+    let mut top_bottom_indent = stack_indent(&edges);
 
-    for (_, window) in windows_iter {
+    for (_, window) in surfaces {
         if edges.contains_key(&ConfigEdge::Top) {
-            window.inner.set_margin(Edge::Top, top_bottom_indent);
+            window.set_margin(Edge::Top, top_bottom_indent);
         } else if edges.contains_key(&ConfigEdge::Bottom) {
-            window.inner.set_margin(Edge::Bottom, top_bottom_indent);
+            window.set_margin(Edge::Bottom, top_bottom_indent);
         }
 
-        top_bottom_indent += window.inner.height()
-            + edges
-                .get(&ConfigEdge::Left)
-                .or_else(|| edges.get(&ConfigEdge::Right))
-                .unwrap_or(&EdgeInfo::default())
-                .margin;
+        top_bottom_indent += window.height() + stack_spacing(&edges);
     }
 }
 
+/// Offset of the first popup from anchored top or bottom edge.
+/// Shared by both per-window and single surface modes
+pub fn stack_indent(edges: &HashMap<ConfigEdge, EdgeInfo>) -> i32 {
+    edges
+        .get(&ConfigEdge::Top)
+        .or_else(|| edges.get(&ConfigEdge::Bottom))
+        .map_or(0, |edge_info| edge_info.padding)
+}
+
+/// Gap between stacked popups
+pub fn stack_spacing(edges: &HashMap<ConfigEdge, EdgeInfo>) -> i32 {
+    edges
+        .get(&ConfigEdge::Left)
+        .or_else(|| edges.get(&ConfigEdge::Right))
+        .unwrap_or(&EdgeInfo::default())
+        .margin
+}
+
+/// Applies reloaded config to everything that is already on screen
+pub fn apply_config(runtime_data: RuntimeData) {
+    let (windows, groups, center, overflow, stack) = {
+        let runtime_data = runtime_data.borrow();
+        let windows: Vec<Window> = runtime_data.windows.values().cloned().collect();
+        let groups: Vec<Group> = runtime_data.groups.values().cloned().collect();
+        (
+            windows,
+            groups,
+            runtime_data.center.clone(),
+            runtime_data.overflow.clone(),
+            runtime_data.stack.clone(),
+        )
+    };
+
+    for window in windows.iter() {
+        window.apply_config();
+    }
+    for group in groups.iter() {
+        group.apply_config();
+    }
+    if let Some(center) = center {
+        center.apply_config();
+    }
+    if let Some(stack) = stack {
+        stack.apply_config();
+    }
+    if let Some(overflow) = overflow {
+        overflow.apply_config();
+    }
+
+    output_update(&runtime_data);
+    layer_update(&runtime_data);
+    margins_update(runtime_data);
+}
+
 pub mod pixbuf {
     use std::path::PathBuf;
 
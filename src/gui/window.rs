@@ -1,44 +1,164 @@
 // TODO probably we need some kind of window factory
 
-use std::{cell::RefCell, path::PathBuf, rc::Rc, time::Duration};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
 
 use gtk::{
-    gdk_pixbuf::Pixbuf,
-    gio,
+    gdk,
     glib::{self, clone, JoinHandle},
-    pango::{self, EllipsizeMode},
     prelude::*,
-    Align, Justification, Orientation,
 };
-use gtk_layer_shell::{KeyboardMode, LayerShell};
+use gtk_layer_shell::{Edge, KeyboardMode, LayerShell};
 #[allow(unused_imports)]
 use log::*;
 
 use crate::{
-    config::CONFIG,
+    config::{keys::Binding, CONFIG},
     dbus::{Action, Details, IFace, IFaceRef, Reason},
+    hooks::{self, Event},
     types::RuntimeData,
 };
 
-use super::utils::{init_layer_shell, pixbuf};
+use super::{
+    card::Card,
+    group::Group,
+    stack::Stack,
+    utils::{init_layer_shell, set_anchors},
+};
+
+/// Where the card is shown
+#[derive(Clone, Default)]
+enum Host {
+    /// Own layer-shell window
+    #[default]
+    Own,
+    Group(Group),
+    Stack(Stack),
+}
 
 #[derive(Clone)]
 pub struct Window {
     pub id: u32,
-    app_name: gtk::Label,
-    icon: gtk::Image,
-    summary: gtk::Label,
-    app_icon: gtk::Image,
-    body: gtk::Label,
-    reply_entry: gtk::Entry,
-    reply_revealer: gtk::Revealer,
-    actions_box: gtk::Box,
-    expire_timeout: Duration,
+    card: Card,
+    /// Wraps card to animate entry and exit
+    revealer: gtk::Revealer,
+    /// Set once closing is started. Exit animation is not interrupted by another close
+    closing: Rc<Cell<Option<Reason>>>,
     thandle: Rc<RefCell<Option<JoinHandle<()>>>>,
+    details: Rc<RefCell<Details>>,
+    skip_history: Rc<Cell<bool>>,
+    /// Own window is not shown if card is moved somewhere else
+    host: Rc<RefCell<Host>>,
     pub inner: gtk::Window,
 }
 
 impl Window {
+    pub fn details(&self) -> Details {
+        self.details.borrow().clone()
+    }
+
+    /// Window will not be recorded into history after closing
+    pub fn set_skip_history(&self, value: bool) {
+        self.skip_history.set(value);
+    }
+
+    pub fn skips_history(&self) -> bool {
+        self.skip_history.get()
+    }
+
+    /// Widget that is put into the hosting surface
+    pub fn widget(&self) -> &gtk::Revealer {
+        &self.revealer
+    }
+
+    pub fn is_grouped(&self) -> bool {
+        matches!(*self.host.borrow(), Host::Group(_))
+    }
+
+    /// Whether card is shown outside of own window
+    pub fn is_embedded(&self) -> bool {
+        !matches!(*self.host.borrow(), Host::Own)
+    }
+
+    /// Layer surface the card is shown on
+    fn surface(&self) -> gtk::Window {
+        match &*self.host.borrow() {
+            Host::Own => self.inner.clone(),
+            Host::Group(group) => group.surface(),
+            Host::Stack(stack) => stack.inner.clone(),
+        }
+    }
+
+    /// Moves card out of own window and closes it without running close hook
+    fn embed(&self, host: Host) {
+        self.host.replace(host);
+        self.inner.set_child(None::<&gtk::Widget>);
+        self.inner.close();
+    }
+
+    /// Moves card into the group
+    pub fn join(&self, group: &Group) {
+        if let Host::Stack(stack) = &*self.host.borrow() {
+            stack.remove(&self.revealer);
+        }
+        self.embed(Host::Group(group.clone()));
+        group.add(self);
+    }
+
+    /// Point from `x` and `y` hints if they are enabled in config
+    pub fn position(&self) -> Option<(i32, i32)> {
+        if !CONFIG.lock().unwrap().position_hints {
+            return None;
+        }
+        let details = self.details.borrow();
+        details.hints.x.zip(details.hints.y)
+    }
+
+    /// Places own window near the point from hints using margins from top-left corner.
+    /// Window is kept inside the monitor. Returns `false` if there is no point
+    pub fn place_at_position(&self) -> bool {
+        let Some((x, y)) = self.position() else {
+            return false;
+        };
+        let config = CONFIG.lock().unwrap().clone();
+        let width = config.window_size.0;
+        let height = self.inner.height().max(config.window_size.1);
+
+        let monitor = config.output.monitor().or_else(|| {
+            gdk::Display::default()?
+                .monitors()
+                .item(0)
+                .and_downcast::<gdk::Monitor>()
+        });
+        let (x, y) = match monitor {
+            Some(monitor) => {
+                let geometry = monitor.geometry();
+                (
+                    (x - geometry.x()).clamp(0, (geometry.width() - width).max(0)),
+                    (y - geometry.y()).clamp(0, (geometry.height() - height).max(0)),
+                )
+            }
+            None => (x.max(0), y.max(0)),
+        };
+
+        for edge in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom] {
+            self.inner
+                .set_anchor(edge, matches!(edge, Edge::Left | Edge::Top));
+        }
+        self.inner.set_margin(Edge::Left, x);
+        self.inner.set_margin(Edge::Top, y);
+        true
+    }
+
+    /// Moves card into the single popups surface
+    pub fn attach(&self, stack: &Stack) {
+        self.embed(Host::Stack(stack.clone()));
+        stack.insert(self.id, &self.revealer);
+    }
+
     pub fn stop_timeout(&self) {
         if let Some(h) = self.thandle.borrow_mut().take() {
             h.abort();
@@ -47,6 +167,12 @@ impl Window {
     }
 
     pub fn start_timeout(&self) {
+        let expire_timeout = self.details.borrow().expire_timeout;
+        if expire_timeout == Duration::MAX {
+            debug!("Window id: {} never expires", self.id);
+            return;
+        }
+
         if self.thandle.borrow().is_none() {
             info!("Starting timeout for window id: {}", self.id);
             self.thandle
@@ -55,7 +181,7 @@ impl Window {
                     #[strong(rename_to=s)]
                     self,
                     async move {
-                        glib::timeout_future(s.expire_timeout).await;
+                        glib::timeout_future(expire_timeout).await;
 
                         s.close(Reason::Expired);
                         info!("Window closed due to timeout for id: {}", s.id);
@@ -77,7 +203,6 @@ impl Window {
         init_layer_shell(&window.inner);
         window.inner.set_application(Some(&application));
 
-        window.setup_reply_handler(details, iface.clone());
         runtime_data
             .borrow_mut()
             .windows
@@ -90,214 +215,108 @@ impl Window {
         window
     }
 
-    fn setup_reply_handler(&self, details: &Details, iface: Rc<IFaceRef>) {
-        self.reply_entry.connect_activate(clone!(
-            #[strong]
-            details,
-            #[strong]
-            iface,
-            move |entry| {
-                if !entry.text().is_empty() {
-                    glib::spawn_future_local(clone!(
-                        #[strong]
-                        entry,
-                        #[strong]
-                        iface,
-                        async move {
-                            IFace::notification_replied(
-                                iface.signal_context(),
-                                details.id,
-                                &entry.text(),
-                            )
-                            .await
-                            .unwrap();
-                        }
-                    ));
-                } else {
-                    // TODO need to somehow notify user in the ui
-                    warn!("The entry cannot be empty!");
-                }
-            }
-        ));
-    }
-
     pub fn update_from_details(&mut self, details: &Details, iface: Rc<IFaceRef>) {
         self.stop_timeout();
-        self.update_labels(details);
-        self.update_icon(details);
-
-        self.reply_entry
-            .set_visible(details.actions.iter().any(|a| a.key == "inline-reply"));
+        self.card.update_from_details(details, iface);
+        self.update_default_action(details);
 
-        self.update_actions(details, iface);
-
-        if let Some(default_action) = details.actions.iter().find(|a| a.key == "default") {
-            self.inner.set_tooltip_text(Some(&default_action.text));
+        self.details.replace(details.clone());
+        if let Host::Group(group) = &*self.host.borrow() {
+            group.refresh();
         }
-
-        self.expire_timeout = details.expire_timeout;
+        self.place_at_position();
         debug!("Window update complete for id: {}", self.id);
     }
 
-    fn update_labels(&mut self, details: &Details) {
-        self.app_name
-            .set_label(details.app_name.as_deref().unwrap_or_default());
-        self.app_name
-            .set_visible(CONFIG.lock().unwrap().show_app_name);
-
-        self.summary.set_label(&details.summary);
-
-        self.body
-            .set_label(details.body.as_deref().unwrap_or_default());
-        self.body.set_visible(details.body.is_some());
+    /// Re-reads settings that can be changed by config reload
+    pub fn apply_config(&self) {
+        let config = CONFIG.lock().unwrap().clone();
+        self.inner
+            .set_default_size(config.window_size.0, config.window_size.1);
+        if !self.place_at_position() {
+            set_anchors(&self.inner, &config.edges);
+        }
+        self.revealer
+            .set_transition_type(config.animation.transition(&config.edges));
+        self.revealer
+            .set_transition_duration(config.animation.duration());
+        self.card.apply_config(&self.details.borrow());
     }
 
-    fn update_icon(&mut self, details: &Details) {
-        let app_info = self.find_app_info(details);
-        self.set_app_icon(app_info);
-        self.set_image_icon(details);
+    fn update_default_action(&self, details: &Details) {
+        if let Some(default_action) = details.actions.iter().find(|a| a.key == "default") {
+            self.card.root.set_tooltip_text(Some(&default_action.text));
+        }
     }
 
-    fn find_app_info(&self, details: &Details) -> Option<gio::DesktopAppInfo> {
-        details
-            .hints
-            .desktop_entry
-            .as_deref()
-            .and_then(|de| {
-                gio::DesktopAppInfo::new(de)
-                    .or_else(|| gio::DesktopAppInfo::new(&format!("{}.desktop", de)))
-            })
-            .or_else(|| {
-                details.app_name.as_deref().and_then(|an| {
-                    gio::DesktopAppInfo::new(an)
-                        .or_else(|| gio::DesktopAppInfo::new(&format!("{}.desktop", an)))
-                        .or_else(|| gio::DesktopAppInfo::new(&an.to_lowercase()))
-                        .or_else(|| {
-                            gio::DesktopAppInfo::new(&format!("{}.desktop", an.to_lowercase()))
-                        })
-                })
-            })
-    }
+    /// Emits `ActionInvoked` for action with given key.
+    /// Default action also closes the window as left click does unless it is resident
+    pub fn invoke_action(&self, key: &str, iface: Rc<IFaceRef>) -> bool {
+        let action = self
+            .details
+            .borrow()
+            .actions
+            .iter()
+            .find(|a| a.key == key)
+            .cloned();
+        let Some(action) = action else {
+            warn!("Window id: {} has no action with key: {}", self.id, key);
+            return false;
+        };
+        hooks::run(Event::Action(action.key.clone()), &self.details());
+
+        glib::spawn_future_local(clone!(
+            #[strong(rename_to=s)]
+            self,
+            async move {
+                if let Err(e) =
+                    IFace::action_invoked(iface.signal_context(), s.id, action.clone()).await
+                {
+                    error!(
+                        "Failed to invoke action: {} for window id: {}. Error: {:?}",
+                        action.key, s.id, e
+                    );
+                }
 
-    fn set_app_icon(&self, app_info: Option<gio::DesktopAppInfo>) {
-        if let Some(icon_name) =
-            app_info.and_then(|app| app.icon().and_then(|icon| icon.to_string()))
-        {
-            if PathBuf::from(icon_name.clone()).is_absolute() {
-                self.app_icon.set_from_file(Some(icon_name));
-            } else {
-                self.app_icon.set_icon_name(Some(&icon_name));
+                if action.key == "default" && !s.details().hints.resident {
+                    s.close(Reason::Dismissed);
+                }
             }
-        } else {
-            self.app_icon
-                .set_icon_name(Some(&CONFIG.lock().unwrap().window_close_icon));
-        }
+        ));
+        true
     }
 
-    fn set_image_icon(&self, details: &Details) {
-        let pixbuf: Option<Pixbuf> = details
-            .hints
-            .image_data
-            .clone()
-            .map(Pixbuf::from)
-            .or_else(|| {
-                details
-                    .hints
-                    .image_path
-                    .as_deref()
-                    .and_then(pixbuf::new_from_str)
-            })
-            .or_else(|| details.app_icon.as_deref().and_then(pixbuf::new_from_str))
-            .or_else(|| details.hints.icon_data.clone().map(Pixbuf::from))
-            .map(|pb| pixbuf::crop_square(&pb));
-
-        self.icon.set_visible(pixbuf.is_some());
-        if let Some(pb) = pixbuf {
-            self.icon.set_from_pixbuf(Some(&pb));
+    /// Animates card out and then closes it
+    pub fn close(&self, reason: Reason) {
+        if self.closing.get().is_some() {
+            return;
         }
-    }
+        self.closing.set(Some(reason));
+        self.stop_timeout();
 
-    fn update_actions(&self, details: &Details, iface: Rc<IFaceRef>) {
-        self.actions_box.set_visible(false);
-        self.actions_box
-            .observe_children()
-            .into_iter()
-            .filter_map(|child| child.ok().and_downcast::<gtk::Widget>())
-            .for_each(|child| self.actions_box.remove(&child));
-        for action in details.actions.iter().filter(|a| a.key != "default") {
-            // let action_button =
-            self.actions_box
-                .append(&self.create_action_button(action, details, iface.clone()));
-            self.actions_box.set_visible(true);
+        if self.revealer.reveals_child() {
+            // finished in `child-revealed` handler
+            self.revealer.set_reveal_child(false);
+        } else {
+            self.finish_close(reason);
         }
     }
 
-    fn create_action_button(
-        &self,
-        action: &Action,
-        details: &Details,
-        iface: Rc<IFaceRef>,
-    ) -> gtk::Button {
-        let details = details.clone();
-
-        let button = gtk::Button::builder().hexpand(true).build();
-        if !details.hints.action_icons {
-            button.set_label(&action.text);
-        } else {
-            let config = CONFIG.lock().unwrap().clone();
-            let redef = config.icons_alias.get(&action.key).unwrap_or(&action.key);
-            button.set_icon_name(redef);
-        }
-        button.set_tooltip_text(Some(&action.text));
-
-        if action.key == "inline-reply" {
-            button.connect_clicked(clone!(
-                #[strong(rename_to=s)]
-                self,
-                move |_| {
-                    if s.reply_entry.text().is_empty() {
-                        s.reply_revealer
-                            .set_reveal_child(!s.reply_revealer.reveals_child());
-                    } else {
-                        s.reply_entry.emit_activate();
-                    }
-                }
-            ));
-        } else {
-            button.connect_clicked(clone!(
-                #[strong]
-                iface,
-                #[strong]
-                action,
-                move |_| {
-                    glib::spawn_future_local(clone!(
-                        #[strong]
-                        iface,
-                        #[strong]
-                        action,
-                        async move {
-                            if let Err(e) = IFace::action_invoked(
-                                iface.signal_context(),
-                                details.id,
-                                action.clone(),
-                            )
-                            .await
-                            {
-                                error!(
-                                    "Failed to invoke action: {} for window id: {}. Error: {:?}",
-                                    action.key, details.id, e
-                                );
-                            }
-                        }
-                    ));
-                }
-            ));
+    fn finish_close(&self, reason: Reason) {
+        let host = self.host.borrow().clone();
+        match host {
+            Host::Own => {}
+            Host::Group(group) => {
+                group.remove(self, reason);
+                return;
+            }
+            Host::Stack(stack) => {
+                stack.remove(&self.revealer);
+                stack.closed(self.id, reason);
+                return;
+            }
         }
 
-        button
-    }
-
-    pub fn close(&self, reason: Reason) {
         unsafe {
             self.inner.set_data("close-reason", reason);
         }
@@ -305,9 +324,10 @@ impl Window {
     }
 
     pub fn has_default_action(&self) -> bool {
-        self.inner
+        self.card
+            .root
             .tooltip_text()
-            .map_or(false, |text| !text.is_empty())
+            .is_some_and(|text| !text.is_empty())
     }
 
     // we are changing keyboard_mode here to proper passive work
@@ -317,224 +337,102 @@ impl Window {
 
     // FIXME probably this is not good idea to check it by css_classes
     pub fn toggle_hover(&self) {
-        if self.inner.has_css_class("hover") {
-            self.inner.set_keyboard_mode(KeyboardMode::None);
-            self.inner.remove_css_class("hover");
+        // group tracks hover for all members
+        if self.is_grouped() {
+            return;
+        }
+
+        let surface = self.surface();
+        if self.card.root.has_css_class("hover") {
+            surface.set_keyboard_mode(KeyboardMode::None);
+            self.card.root.remove_css_class("hover");
             self.start_timeout();
         } else {
-            self.inner.set_keyboard_mode(KeyboardMode::OnDemand);
-            self.inner.add_css_class("hover");
+            surface.set_keyboard_mode(KeyboardMode::OnDemand);
+            self.card.root.add_css_class("hover");
             self.stop_timeout();
         }
     }
 
-    fn build_widgets_tree(details: &Details) -> Self {
+    fn build_widgets_tree(details: &Details, iface: Rc<IFaceRef>) -> Self {
         let config = CONFIG.lock().unwrap().clone();
 
         let inner = gtk::Window::builder()
             .default_width(config.window_size.0)
             .default_height(config.window_size.1)
-            .name("notification")
-            .build();
-
-        let app_name = gtk::Label::builder()
-            .name("app_name")
-            .justify(Justification::Left)
-            .halign(Align::Start)
-            .ellipsize(EllipsizeMode::End)
-            .sensitive(false)
-            .build();
-        let app_icon = gtk::Image::builder()
-            .name("app_icon")
-            .hexpand(true)
-            .halign(Align::End)
-            .visible(true)
-            .build();
-        let app_name_box = gtk::Box::builder()
-            .orientation(Orientation::Horizontal)
-            .spacing(5)
-            .visible(CONFIG.lock().unwrap().show_app_name)
-            .build();
-        app_name_box.append(&app_name);
-        if CONFIG.lock().unwrap().show_app_name {
-            app_name_box.append(&app_icon);
-        }
-
-        let summary = gtk::Label::builder()
-            .name("summary")
-            .justify(Justification::Left)
-            .halign(Align::Start)
-            .ellipsize(EllipsizeMode::End)
-            .use_markup(true)
-            .build();
-
-        let icon = gtk::Image::builder()
-            .name("image")
-            .visible(false)
-            .pixel_size(CONFIG.lock().unwrap().icon_size)
-            .valign(Align::Center)
-            .halign(Align::End)
-            .build();
-
-        let summary_box = gtk::Box::builder()
-            .orientation(Orientation::Horizontal)
-            .spacing(5)
-            .build();
-        summary_box.append(&summary);
-        if !CONFIG.lock().unwrap().show_app_name {
-            summary_box.append(&app_icon);
-        }
-
-        let body = gtk::Label::builder()
-            .name("body")
-            .justify(Justification::Left)
-            .valign(Align::Fill)
-            .halign(Align::Start)
-            .wrap(true)
-            .wrap_mode(pango::WrapMode::WordChar)
-            .use_markup(true)
             .build();
+        inner.add_css_class("popup");
 
-        let reply_entry = gtk::Entry::builder()
-            .name("reply-entry")
-            .placeholder_text("Reply")
-            .build();
-        let reply_revealer = gtk::Revealer::builder()
-            .name("reply-revealer")
+        let card = Card::new(details, iface);
+        // revealed as soon as it is mapped
+        let revealer = gtk::Revealer::builder()
+            .transition_type(config.animation.transition(&config.edges))
+            .transition_duration(config.animation.duration())
             .reveal_child(false)
-            .child(&reply_entry)
-            .build();
-
-        let actions_box = gtk::Box::builder()
-            .name("actions")
-            .orientation(Orientation::Horizontal)
-            .spacing(5)
-            .build();
-
-        let content = gtk::Box::builder()
-            .name("content")
-            .orientation(Orientation::Vertical)
-            .valign(Align::Start)
-            .spacing(5)
+            .child(&card.root)
             .build();
-        content.append(&app_name_box);
-        content.append(&summary_box);
-        content.append(&body);
-        content.append(&reply_revealer);
-
-        let body_box = gtk::Box::builder()
-            .orientation(Orientation::Horizontal)
-            .spacing(5)
-            .build();
-        body_box.append(&icon);
-        body_box.append(&content);
-
-        let main_box = gtk::Box::builder()
-            .orientation(Orientation::Vertical)
-            .spacing(5)
-            .margin_top(5)
-            .margin_start(5)
-            .margin_bottom(5)
-            .margin_end(5)
-            .build();
-        main_box.append(&body_box);
-        main_box.append(&actions_box);
-
-        inner.set_child(Some(&main_box));
+        inner.set_child(Some(&revealer));
 
         Self {
             id: details.id,
-            app_name,
-            app_icon,
-            icon,
-            summary,
-            body,
-            reply_entry,
-            reply_revealer,
-            actions_box,
-            expire_timeout: details.expire_timeout,
+            card,
+            revealer,
+            closing: Default::default(),
             thandle: Default::default(),
+            details: Rc::new(RefCell::new(details.clone())),
+            skip_history: Default::default(),
+            host: Default::default(),
             inner,
         }
     }
 
     pub fn from_details(value: Details, iface: Rc<IFaceRef>) -> Self {
-        let mut _self = Self::build_widgets_tree(&value);
-        _self.update_from_details(&value, iface.clone());
+        let _self = Self::build_widgets_tree(&value, iface.clone());
+        _self.update_default_action(&value);
 
-        // close_button_events
-        let gesture_click = gtk::GestureClick::builder().build();
-        _self.app_icon.add_controller(gesture_click.clone());
-        gesture_click.connect_released(clone!(
+        _self.card.connect_dismiss(clone!(
             #[strong(rename_to=s)]
             _self,
-            move |gesture, _, _, _| {
-                s.close(Reason::Dismissed);
-
-                gesture.set_state(gtk::EventSequenceState::Claimed);
-            }
+            move || s.close(Reason::Dismissed)
         ));
 
-        let event_conntroller_motion = gtk::EventControllerMotion::new();
-        _self
-            .app_icon
-            .add_controller(event_conntroller_motion.clone());
-
-        event_conntroller_motion.connect_enter(clone!(
-            #[strong(rename_to=app_icon)]
-            _self.app_icon,
-            move |_, _, _| {
-                if app_icon.icon_name().is_some() || app_icon.file().is_some() {
-                    if let Some(icon_name) = app_icon.icon_name() {
-                        unsafe {
-                            app_icon.set_data("icon-name", icon_name);
-                        }
-                    }
-                    if let Some(file) = app_icon.file() {
-                        unsafe {
-                            app_icon.set_data("file", file);
-                        }
-                    }
+        _self.revealer.connect_map(clone!(
+            #[strong(rename_to=s)]
+            _self,
+            move |revealer| {
+                if s.closing.get().is_none() {
+                    revealer.set_reveal_child(true);
                 }
-
-                app_icon.set_icon_name(Some(&CONFIG.lock().unwrap().window_close_icon.clone()));
             }
         ));
-
-        event_conntroller_motion.connect_leave(clone!(
-            #[strong(rename_to=app_icon)]
-            _self.app_icon,
-            move |_| {
-                let icon_name = unsafe {
-                    app_icon
-                        .data::<glib::GString>("icon-name")
-                        .map(|v| v.as_ref().clone())
-                };
-                let file = unsafe {
-                    app_icon
-                        .data::<glib::GString>("file")
-                        .map(|v| v.as_ref().clone())
-                };
-
-                if let Some(icon_name) = icon_name {
-                    app_icon.set_icon_name(Some(&icon_name));
-                } else {
-                    app_icon.set_from_file(file);
+        _self.revealer.connect_child_revealed_notify(clone!(
+            #[strong(rename_to=s)]
+            _self,
+            move |revealer| {
+                if revealer.is_child_revealed() {
+                    return;
+                }
+                if let Some(reason) = s.closing.get() {
+                    s.finish_close(reason);
                 }
             }
         ));
-        // close_button_events_end
 
         // hover events
 
         // FIXME new window breaks focus
         // it invokes leave and notification can be lost while we are "holding" it
         let event_controller_motion = gtk::EventControllerMotion::new();
-        _self.inner.add_controller(event_controller_motion.clone());
+        _self
+            .card
+            .root
+            .add_controller(event_controller_motion.clone());
         event_controller_motion.connect_enter(clone!(
             #[strong(rename_to=s)]
             _self,
             move |_, _, _| {
+                // key events are delivered through focused widget
+                s.card.root.grab_focus();
                 s.toggle_hover();
             }
         ));
@@ -546,11 +444,53 @@ impl Window {
             }
         ));
 
+        // keyboard
+        _self.card.root.set_focusable(true);
+        let event_controller_key = gtk::EventControllerKey::new();
+        _self.card.root.add_controller(event_controller_key.clone());
+        event_controller_key.connect_key_pressed(clone!(
+            #[strong]
+            iface,
+            #[strong(rename_to=s)]
+            _self,
+            move |_, key, _, state| {
+                let binding = CONFIG.lock().unwrap().keys.binding(key, state);
+                let Some(binding) = binding else {
+                    return glib::Propagation::Proceed;
+                };
+                debug!("Key binding {:?} for window id: {}", binding, s.id);
+
+                let handled = match binding {
+                    Binding::Dismiss => {
+                        s.close(Reason::Dismissed);
+                        true
+                    }
+                    Binding::DefaultAction => {
+                        s.has_default_action() && s.invoke_action("default", iface.clone())
+                    }
+                    Binding::Action(index) => s.card.activate_action(index),
+                    Binding::NextAction => {
+                        s.card.focus_action(true);
+                        true
+                    }
+                    Binding::PrevAction => {
+                        s.card.focus_action(false);
+                        true
+                    }
+                };
+                if handled {
+                    glib::Propagation::Stop
+                } else {
+                    glib::Propagation::Proceed
+                }
+            }
+        ));
+
         // click_gestures
 
         // lmb
         let gesture_click_l = gtk::GestureClick::builder().button(1).build();
-        _self.inner.add_controller(gesture_click_l.clone());
+        _self.card.root.add_controller(gesture_click_l.clone());
         gesture_click_l.connect_released(clone!(
             #[strong]
             iface,
@@ -565,6 +505,7 @@ impl Window {
                     s,
                     async move {
                         if s.has_default_action() {
+                            hooks::run(Event::Action(Action::default().key), &s.details());
                             IFace::action_invoked(
                                 iface.signal_context(),
                                 value.id,
@@ -573,7 +514,9 @@ impl Window {
                             .await
                             .unwrap();
 
-                            s.close(Reason::Dismissed);
+                            if !s.details().hints.resident {
+                                s.close(Reason::Dismissed);
+                            }
                         }
                     }
                 ));
@@ -584,7 +527,7 @@ impl Window {
 
         // rmb
         let gesture_click_r = gtk::GestureClick::builder().button(3).build();
-        _self.inner.add_controller(gesture_click_r.clone());
+        _self.card.root.add_controller(gesture_click_r.clone());
         gesture_click_r.connect_released(clone!(
             #[strong(rename_to=s)]
             _self,
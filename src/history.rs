@@ -0,0 +1,403 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use gtk::glib;
+#[allow(unused_imports)]
+use log::*;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    config::{self, CONFIG},
+    dbus::{Action, Category, Details, Hints, Reason, Urgency},
+};
+
+/// Snapshot of a notification as it was shown to the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: u32,
+    pub app_name: Option<String>,
+    pub app_icon: Option<String>,
+    pub desktop_entry: Option<String>,
+    pub summary: String,
+    pub body: Option<String>,
+    /// (key, text) pairs
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
+    /// Full category like `email.arrived`
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Unix timestamp of notification arrival
+    pub created: i64,
+    /// Unix timestamp of notification closing
+    pub closed: Option<i64>,
+    pub reason: Option<Reason>,
+}
+
+impl Entry {
+    pub fn new(details: &Details, reason: Option<Reason>) -> Self {
+        Self {
+            id: details.id,
+            app_name: details.app_name.clone(),
+            app_icon: details.app_icon.clone(),
+            desktop_entry: details.hints.desktop_entry.clone(),
+            summary: details.summary.clone(),
+            body: details.body.clone(),
+            actions: details
+                .actions
+                .iter()
+                .map(|a| (a.key.clone(), a.text.clone()))
+                .collect(),
+            urgency: details.hints.urgency,
+            category: details.hints.category.as_ref().map(|c| c.to_string()),
+            created: details.timestamp.unix_timestamp(),
+            closed: reason.map(|_| OffsetDateTime::now_utc().unix_timestamp()),
+            reason,
+        }
+    }
+
+    /// Whether entry has given category or category class
+    pub fn has_category(&self, name: &str) -> bool {
+        self.category
+            .as_deref()
+            .is_some_and(|c| Category::from(c).matches(name))
+    }
+
+    /// Restores displayable notification. Images are not stored so they are lost
+    pub fn to_details(&self) -> Details {
+        Details {
+            id: self.id,
+            app_name: self.app_name.clone(),
+            app_icon: self.app_icon.clone(),
+            summary: self.summary.clone(),
+            body: self.body.clone(),
+            actions: self
+                .actions
+                .iter()
+                .map(|(key, text)| Action::new(key, text))
+                .collect(),
+            hints: Hints {
+                desktop_entry: self.desktop_entry.clone(),
+                urgency: self.urgency,
+                category: self.category.as_deref().map(Category::from),
+                ..Default::default()
+            },
+            expire_timeout: Duration::MAX,
+            timestamp: OffsetDateTime::from_unix_timestamp(self.created)
+                .unwrap_or(OffsetDateTime::UNIX_EPOCH),
+            css_classes: Vec::new(),
+            group: None,
+        }
+    }
+}
+
+/// Bursts of changes (e.g. dismissing all) are merged into a single write
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<Entry>,
+    path: PathBuf,
+    /// Started on first save
+    writer: Option<Writer>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            path: Self::path(),
+            writer: None,
+        }
+    }
+}
+
+/// Writes history file on a separate thread so closing notifications doesn't block UI
+#[derive(Debug)]
+struct Writer {
+    sender: mpsc::Sender<VecDeque<Entry>>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl Writer {
+    fn spawn(path: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel::<VecDeque<Entry>>();
+        let thread = thread::spawn(move || {
+            while let Ok(mut entries) = receiver.recv() {
+                thread::sleep(SAVE_DELAY);
+                // only the latest state matters
+                while let Ok(newer) = receiver.try_recv() {
+                    entries = newer;
+                }
+                write(&path, &entries);
+            }
+        });
+
+        Self { sender, thread }
+    }
+}
+
+impl History {
+    fn path() -> PathBuf {
+        glib::user_data_dir().join("rustyfications/history.ron")
+    }
+
+    pub fn load() -> Self {
+        let config = CONFIG.lock().unwrap().history.clone();
+        Self::load_from(Self::path(), &config)
+    }
+
+    fn load_from(path: PathBuf, config: &config::history::History) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(s) => ron::from_str::<VecDeque<Entry>>(&s).unwrap_or_else(|e| {
+                error!("Failed to parse history file {:?}: {}", path, e);
+                VecDeque::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+            Err(e) => {
+                error!("Failed to read history file {:?}: {}", path, e);
+                VecDeque::new()
+            }
+        };
+
+        let mut history = Self {
+            entries,
+            path,
+            writer: None,
+        };
+        history.prune(config);
+        info!("Loaded {} history entries", history.entries.len());
+        history
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        let config = CONFIG.lock().unwrap().history.clone();
+        self.push_with(entry, &config);
+    }
+
+    fn push_with(&mut self, entry: Entry, config: &config::history::History) {
+        if !config.enabled {
+            return;
+        }
+
+        debug!("Adding history entry for id: {}", entry.id);
+        // `replaces_id` reuses ids, so only the latest state is kept
+        self.entries.retain(|e| e.id != entry.id);
+        self.entries.push_back(entry);
+        self.prune(config);
+        self.save();
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Entry> {
+        let index = self.entries.iter().position(|e| e.id == id)?;
+        let entry = self.entries.remove(index);
+        self.save();
+        entry
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.save();
+    }
+
+    /// Entries from oldest to newest
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    /// Biggest id ever stored. Used to continue ids numbering after restart
+    pub fn last_id(&self) -> u32 {
+        self.entries.iter().map(|e| e.id).max().unwrap_or_default()
+    }
+
+    fn prune(&mut self, config: &config::history::History) {
+        let oldest = OffsetDateTime::now_utc().unix_timestamp() - config.max_age as i64;
+        self.entries.retain(|e| e.created >= oldest);
+
+        while self.entries.len() > config.max_entries {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Waits for pending write. Must be called before exit
+    pub fn flush(&mut self) {
+        if let Some(Writer { sender, thread }) = self.writer.take() {
+            drop(sender);
+            if thread.join().is_err() {
+                error!("History writer thread panicked");
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        let writer = self
+            .writer
+            .get_or_insert_with(|| Writer::spawn(self.path.clone()));
+        if writer.sender.send(self.entries.clone()).is_err() {
+            error!("History writer thread is gone. History is not saved");
+        }
+    }
+}
+
+fn write(path: &Path, entries: &VecDeque<Entry>) {
+    let result = ron::to_string(entries)
+        .map_err(io::Error::other)
+        .and_then(|s| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            // write and rename to not lose the whole history on crash
+            let tmp = path.with_extension("ron.tmp");
+            fs::write(&tmp, s)?;
+            fs::rename(&tmp, path)
+        });
+
+    if let Err(e) = result {
+        error!("Failed to save history file {:?}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn entry(id: u32, age: i64) -> Entry {
+        Entry {
+            id,
+            app_name: Some("app".to_owned()),
+            app_icon: None,
+            desktop_entry: None,
+            summary: format!("summary {}", id),
+            body: None,
+            actions: Vec::new(),
+            urgency: Urgency::Normal,
+            category: None,
+            created: OffsetDateTime::now_utc().unix_timestamp() - age,
+            closed: None,
+            reason: Some(Reason::Dismissed),
+        }
+    }
+
+    fn history(dir: &TempDir) -> History {
+        History {
+            path: dir.path().join("history.ron"),
+            ..Default::default()
+        }
+    }
+
+    fn ids(history: &History) -> Vec<u32> {
+        history.entries().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn push_keeps_latest_state_of_id() {
+        let dir = TempDir::new().unwrap();
+        let mut history = history(&dir);
+        let config = config::history::History::default();
+
+        history.push_with(entry(1, 0), &config);
+        history.push_with(entry(2, 0), &config);
+        let mut replaced = entry(1, 0);
+        replaced.summary = "replaced".to_owned();
+        history.push_with(replaced, &config);
+
+        assert_eq!(ids(&history), [2, 1]);
+        assert_eq!(history.entries().last().unwrap().summary, "replaced");
+        assert_eq!(history.last_id(), 2);
+    }
+
+    #[test]
+    fn push_is_ignored_when_disabled() {
+        let dir = TempDir::new().unwrap();
+        let mut history = history(&dir);
+        let config = config::history::History {
+            enabled: false,
+            ..Default::default()
+        };
+
+        history.push_with(entry(1, 0), &config);
+        assert!(ids(&history).is_empty());
+    }
+
+    #[test]
+    fn oldest_entries_are_trimmed() {
+        let dir = TempDir::new().unwrap();
+        let mut history = history(&dir);
+        let config = config::history::History {
+            max_entries: 2,
+            ..Default::default()
+        };
+
+        for id in 1..=3 {
+            history.push_with(entry(id, 0), &config);
+        }
+        assert_eq!(ids(&history), [2, 3]);
+    }
+
+    #[test]
+    fn expired_entries_are_trimmed() {
+        let dir = TempDir::new().unwrap();
+        let mut history = history(&dir);
+        let config = config::history::History {
+            max_age: 60,
+            ..Default::default()
+        };
+
+        history.push_with(entry(1, 120), &config);
+        history.push_with(entry(2, 30), &config);
+        assert_eq!(ids(&history), [2]);
+    }
+
+    #[test]
+    fn saved_history_is_loaded_back() {
+        let dir = TempDir::new().unwrap();
+        let mut history = history(&dir);
+        let config = config::history::History::default();
+
+        for id in 1..=3 {
+            history.push_with(entry(id, 0), &config);
+        }
+        history.remove(2);
+        history.flush();
+
+        let loaded = History::load_from(history.path.clone(), &config);
+        assert_eq!(ids(&loaded), [1, 3]);
+        assert_eq!(
+            loaded.entries().collect::<Vec<_>>(),
+            history.entries().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn loading_prunes_stored_entries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("history.ron");
+        write(&path, &VecDeque::from([entry(1, 120), entry(2, 0)]));
+
+        let config = config::history::History {
+            max_age: 60,
+            ..Default::default()
+        };
+        assert_eq!(ids(&History::load_from(path, &config)), [2]);
+    }
+
+    #[test]
+    fn missing_or_broken_file_is_empty_history() {
+        let dir = TempDir::new().unwrap();
+        let config = config::history::History::default();
+        let path = dir.path().join("history.ron");
+
+        assert!(ids(&History::load_from(path.clone(), &config)).is_empty());
+
+        fs::write(&path, "not ron").unwrap();
+        assert!(ids(&History::load_from(path, &config)).is_empty());
+    }
+}
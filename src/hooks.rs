@@ -0,0 +1,176 @@
+use std::{ffi::OsStr, time::Duration};
+
+use gtk::{gio, glib};
+#[allow(unused_imports)]
+use log::*;
+use serde::Serialize;
+
+use crate::{
+    config::{hooks::Hooks, CONFIG},
+    dbus::{Details, NotificationInfo, Reason},
+    history::Entry,
+};
+
+/// Notification lifecycle event that can trigger user command
+#[derive(Debug, Clone)]
+pub enum Event {
+    Notify,
+    Replace,
+    Close(Reason),
+    Action(String),
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::Notify => "notify",
+            Event::Replace => "replace",
+            Event::Close(_) => "close",
+            Event::Action(_) => "action",
+        }
+    }
+
+    fn command<'a>(&self, hooks: &'a Hooks) -> Option<&'a String> {
+        match self {
+            Event::Notify => hooks.on_notify.as_ref(),
+            Event::Replace => hooks.on_replace.as_ref(),
+            Event::Close(_) => hooks.on_close.as_ref(),
+            Event::Action(_) => hooks.on_action.as_ref(),
+        }
+    }
+
+    fn reason(&self) -> Option<Reason> {
+        match self {
+            Event::Close(reason) => Some(*reason),
+            _ => None,
+        }
+    }
+
+    fn action(&self) -> Option<&str> {
+        match self {
+            Event::Action(key) => Some(key),
+            _ => None,
+        }
+    }
+}
+
+/// What hook receives on stdin
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    #[serde(flatten)]
+    notification: &'a NotificationInfo,
+    app_icon: Option<&'a str>,
+    desktop_entry: Option<&'a str>,
+    action: Option<&'a str>,
+}
+
+/// Spawns configured command for the event without waiting for it
+pub fn run(event: Event, details: &Details) {
+    let (command, timeout) = {
+        let config = CONFIG.lock().unwrap();
+        (event.command(&config.hooks).cloned(), config.hooks.timeout)
+    };
+    let Some(command) = command else {
+        return;
+    };
+
+    let argv = match glib::shell_parse_argv(&command) {
+        Ok(argv) => argv,
+        Err(e) => {
+            error!(
+                "Failed to parse {} hook command \"{}\": {}",
+                event.name(),
+                command,
+                e
+            );
+            return;
+        }
+    };
+
+    let info = NotificationInfo::from(&Entry::new(details, event.reason()));
+    let payload = Payload {
+        event: event.name(),
+        notification: &info,
+        app_icon: details.app_icon.as_deref(),
+        desktop_entry: details.hints.desktop_entry.as_deref(),
+        action: event.action(),
+    };
+    let payload = match serde_json::to_string(&payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("Failed to serialize {} hook payload: {}", event.name(), e);
+            return;
+        }
+    };
+
+    let launcher = gio::SubprocessLauncher::new(gio::SubprocessFlags::STDIN_PIPE);
+    launcher.setenv("RUSTYFICATIONS_EVENT", event.name(), true);
+    launcher.setenv("RUSTYFICATIONS_ID", info.id.to_string(), true);
+    launcher.setenv("RUSTYFICATIONS_APP_NAME", &info.app_name, true);
+    launcher.setenv("RUSTYFICATIONS_SUMMARY", &info.summary, true);
+    launcher.setenv("RUSTYFICATIONS_BODY", &info.body, true);
+    launcher.setenv("RUSTYFICATIONS_URGENCY", info.urgency.to_string(), true);
+    if let Some(reason) = event.reason() {
+        launcher.setenv("RUSTYFICATIONS_REASON", (reason as u32).to_string(), true);
+    }
+    if let Some(action) = event.action() {
+        launcher.setenv("RUSTYFICATIONS_ACTION", action, true);
+    }
+
+    let argv: Vec<&OsStr> = argv.iter().map(|arg| arg.as_os_str()).collect();
+    let process = match launcher.spawn(&argv) {
+        Ok(process) => process,
+        Err(e) => {
+            error!(
+                "Failed to spawn {} hook \"{}\": {}",
+                event.name(),
+                command,
+                e
+            );
+            return;
+        }
+    };
+    debug!(
+        "Spawned {} hook for notification id: {}",
+        event.name(),
+        info.id
+    );
+
+    glib::spawn_future_local(async move {
+        let result = glib::future_with_timeout(
+            Duration::from_millis(timeout),
+            process.communicate_utf8_future(Some(payload)),
+        )
+        .await;
+
+        match result {
+            Ok(Ok(_)) if process.is_successful() => {
+                debug!("{} hook finished successfully", event.name());
+            }
+            Ok(Ok(_)) if process.has_signaled() => {
+                warn!(
+                    "{} hook was killed by signal {}",
+                    event.name(),
+                    process.term_sig()
+                );
+            }
+            Ok(Ok(_)) => {
+                warn!(
+                    "{} hook exited with status {}",
+                    event.name(),
+                    process.exit_status()
+                );
+            }
+            Ok(Err(e)) => error!("Failed to communicate with {} hook: {}", event.name(), e),
+            Err(_) => {
+                warn!(
+                    "{} hook timed out after {}ms and will be killed",
+                    event.name(),
+                    timeout
+                );
+                process.force_exit();
+            }
+        }
+    });
+}
@@ -1,59 +1,124 @@
+mod cli;
 mod config;
 mod dbus;
+mod dnd;
+mod fullscreen;
 mod gui;
+mod history;
+mod hooks;
+mod sound;
+mod state;
 mod types;
 mod utils;
 
-use std::{error::Error, rc::Rc, sync::Arc, time::Duration};
+use std::{env, error::Error, process, rc::Rc, sync::Arc};
 
-use config::CONFIG;
-use dbus::{Details, IFace, IFaceRef, Message, Reason, ServerInfo};
+use cli::{Args, Command};
+use config::{Config, CONFIG};
+use dbus::{Details, IFace, IFaceRef, Id, Message, NotificationInfo, Reason, Request, ServerInfo};
 use futures::{
     channel::mpsc::{self, Receiver},
     lock::Mutex,
     StreamExt,
 };
 use gtk::{
+    gdk, gio,
     glib::{self, clone},
     prelude::*,
 };
-use gui::{build_ui, utils::margins_update, window::Window};
+use gui::{
+    build_ui,
+    center::{self, Center},
+    group,
+    overflow::{self, Overflow},
+    stack::Stack,
+    utils::{apply_config, layer_update, margins_update, margins_update_on_map, output_update},
+    window::Window,
+};
+use history::{Entry, History};
 #[allow(unused_imports)]
 use log::*;
+use state::State;
 use types::RuntimeData;
-use utils::{close_hook, logger_init, setup_styling};
+use utils::{close_hook, logger_init, setup_styling, setup_user_styling};
 
 pub static MAIN_APP_ID: &str = "com.bzglve.rustyfications";
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    match args.command {
+        Command::Daemon => daemon(),
+        _ => {
+            if let Err(e) = cli::run(args) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn daemon() -> Result<(), Box<dyn Error>> {
     logger_init()?;
 
     info!("Starting application...");
 
     let runtime_data = RuntimeData::default();
+    runtime_data.borrow_mut().history = History::load();
+    Id::init_glob(runtime_data.borrow().history.last_id());
+    if CONFIG.lock().unwrap().dnd.persist {
+        runtime_data.borrow_mut().dnd = State::load().dnd;
+    }
 
     let application = gtk::Application::new(Some(MAIN_APP_ID), Default::default());
 
     let (sender, receiver) = mpsc::channel(100);
     let receiver = Arc::new(Mutex::new(receiver));
 
-    let iface = Rc::new(
-        IFace::new(
-            ServerInfo::new(
-                env!("CARGO_PKG_NAME"),
-                env!("CARGO_PKG_AUTHORS"),
-                env!("CARGO_PKG_VERSION"),
-                "1.2",
-            ),
-            sender,
-        )
-        .connect()?,
-    );
+    let (iface, control) = IFace::new(
+        ServerInfo::new(
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_AUTHORS"),
+            env!("CARGO_PKG_VERSION"),
+            "1.2",
+        ),
+        sender,
+    )
+    .connect()?;
+    let iface = Rc::new(iface);
+    let control = Rc::new(control);
+
+    // pending history write is finished on regular termination
+    application.connect_shutdown(clone!(
+        #[strong]
+        runtime_data,
+        move |_| runtime_data.borrow_mut().history.flush()
+    ));
 
     application.connect_startup(move |application| {
         info!("Application startup initiated.");
 
-        setup_styling();
+        setup_styling(&runtime_data);
+        setup_user_styling(&runtime_data);
+
+        let center = Center::build(application, iface.clone(), runtime_data.clone());
+        runtime_data.borrow_mut().center = Some(center.clone());
+        // `gapplication action com.bzglve.rustyfications toggle-center`
+        application.add_action_entries([gio::ActionEntry::builder("toggle-center")
+            .activate(move |_: &gtk::Application, _, _| center.toggle())
+            .build()]);
+
+        if CONFIG.lock().unwrap().single_surface {
+            let stack = Stack::build(application, iface.clone(), runtime_data.clone());
+            runtime_data.borrow_mut().stack = Some(stack);
+        }
+
+        let overflow = Overflow::build(application, iface.clone(), runtime_data.clone());
+        runtime_data.borrow_mut().overflow = Some(overflow);
 
         handle_notification(
             application.clone(),
@@ -62,11 +127,52 @@ fn main() -> Result<(), Box<dyn Error>> {
             runtime_data.clone(),
         );
 
+        // configured output can be plugged or unplugged
+        if let Some(display) = gdk::Display::default() {
+            display.monitors().connect_items_changed(clone!(
+                #[strong]
+                runtime_data,
+                move |_, _, _, _| output_update(&runtime_data)
+            ));
+        }
+
+        let config_monitors = Config::watch(clone!(
+            #[strong]
+            runtime_data,
+            move || {
+                let _ = reload_config(runtime_data.clone());
+            }
+        ));
+        runtime_data.borrow_mut().config_monitors = config_monitors;
+
+        dnd::watch_quiet_hours(
+            application.clone(),
+            iface.clone(),
+            control.clone(),
+            runtime_data.clone(),
+        );
+
         debug!("CONFIG: {:#?}", CONFIG.lock().unwrap());
     });
 
     application.connect_activate(build_ui);
 
+    for signal in [libc::SIGTERM, libc::SIGINT] {
+        glib::unix_signal_add_local(
+            signal,
+            clone!(
+                #[weak]
+                application,
+                #[upgrade_or]
+                glib::ControlFlow::Break,
+                move || {
+                    application.quit();
+                    glib::ControlFlow::Break
+                }
+            ),
+        );
+    }
+
     application.run();
 
     info!("Application terminated.");
@@ -84,10 +190,19 @@ fn handle_notification(
             let input = receiver.lock().await.select_next_some().await;
             debug!("Received input: {:?}", input);
 
+            match &input {
+                Message::New(details) => {
+                    hooks::run(hooks::Event::Notify, details);
+                    sound::play(details, &runtime_data);
+                }
+                Message::Replace(details) => hooks::run(hooks::Event::Replace, details),
+                _ => {}
+            }
+
             match input {
                 Message::New(details) => {
                     info!("New notification");
-                    new_notification(
+                    show_notification(
                         details,
                         application.clone(),
                         iface.clone(),
@@ -104,8 +219,32 @@ fn handle_notification(
                                 details.id
                             );
                             window.update_from_details(&details, iface.clone());
+                            // urgency could be changed
+                            layer_update(&runtime_data);
 
                             window.start_timeout();
+                            center::refresh(&runtime_data);
+                        }
+                        None if overflow::is_queued(details.id, &runtime_data) => {
+                            debug!("Replacing queued notification with id: {}", details.id);
+                            overflow::replace(details, &runtime_data);
+                        }
+                        None if fullscreen::is_queued(details.id, &runtime_data) => {
+                            debug!("Replacing queued notification with id: {}", details.id);
+                            fullscreen::replace(details, &runtime_data);
+                        }
+                        None if dnd::is_queued(details.id, &runtime_data) => {
+                            debug!("Replacing queued notification with id: {}", details.id);
+                            dnd::replace(details, &runtime_data);
+                        }
+                        None if dnd::suppresses(&details, &runtime_data) => {
+                            dnd::queue(details, &runtime_data);
+                        }
+                        None if fullscreen::suppresses(&details, &runtime_data) => {
+                            fullscreen::queue(details, &runtime_data);
+                        }
+                        None if overflow::should_queue(&details, &runtime_data) => {
+                            overflow::queue(details, &runtime_data);
                         }
                         None => {
                             warn!(
@@ -123,15 +262,135 @@ fn handle_notification(
                 }
                 Message::Close(id) => {
                     info!("Closing notification with id: {}", id);
-                    if let Some(w) = runtime_data.borrow().windows.get(&id) {
-                        w.close(Reason::Closed)
+                    let window = runtime_data.borrow().windows.get(&id).cloned();
+                    match window {
+                        Some(w) => w.close(Reason::Closed),
+                        None => {
+                            if !remove_queued(id, Reason::Closed, iface.clone(), &runtime_data) {
+                                debug!("No notification with id: {} to close", id);
+                            }
+                        }
                     }
                 }
+                Message::Control(request) => {
+                    handle_control(
+                        request,
+                        application.clone(),
+                        iface.clone(),
+                        runtime_data.clone(),
+                    );
+                }
             }
         }
     });
 }
 
+fn handle_control(
+    request: Request,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) {
+    match request {
+        Request::List(tx) => {
+            let list = runtime_data
+                .borrow()
+                .windows
+                .values()
+                .map(|w| NotificationInfo::from(&Entry::new(&w.details(), None)))
+                .collect();
+            let _ = tx.send(list);
+        }
+        Request::History(category, tx) => {
+            let list = runtime_data
+                .borrow()
+                .history
+                .entries()
+                .filter(|e| category.as_deref().is_none_or(|c| e.has_category(c)))
+                .map(NotificationInfo::from)
+                .collect();
+            let _ = tx.send(list);
+        }
+        Request::Dismiss(id, tx) => {
+            let window = runtime_data.borrow().windows.get(&id).cloned();
+            let found = match window {
+                Some(window) => {
+                    window.close(Reason::Dismissed);
+                    true
+                }
+                None => remove_queued(id, Reason::Dismissed, iface, &runtime_data),
+            };
+            let _ = tx.send(found);
+        }
+        Request::DismissAll(tx) => {
+            let windows: Vec<Window> = runtime_data.borrow().windows.values().cloned().collect();
+            for window in windows.iter() {
+                window.close(Reason::Dismissed);
+            }
+            let _ = tx.send(windows.len() as u32);
+        }
+        Request::InvokeAction(id, key, tx) => {
+            let window = runtime_data.borrow().windows.get(&id).cloned();
+            let _ = tx.send(window.is_some_and(|w| w.invoke_action(&key, iface)));
+        }
+        Request::SetDnd(value, tx) => {
+            let enabled = value.unwrap_or(!runtime_data.borrow().dnd);
+            dnd::set(enabled, application, iface, runtime_data);
+            let _ = tx.send(enabled);
+        }
+        Request::SetFullscreen(output, active, tx) => {
+            fullscreen::set(output, active, application, iface, runtime_data);
+            let _ = tx.send(());
+        }
+        Request::Reload(tx) => {
+            let result = reload_config(runtime_data).map_err(|e| e.to_string());
+            let _ = tx.send(result);
+        }
+        Request::ToggleCenter(tx) => {
+            let center = runtime_data.borrow().center.clone();
+            if let Some(center) = center {
+                center.toggle();
+            }
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// Invalid config is not applied, previous one stays in use
+fn reload_config(runtime_data: RuntimeData) -> Result<(), config::Error> {
+    Config::reload().inspect_err(|e| error!("{}", e))?;
+    info!("Configuration reloaded");
+    setup_user_styling(&runtime_data);
+    apply_config(runtime_data);
+    Ok(())
+}
+
+/// Closes notification that is held back by popups limit, fullscreen or do-not-disturb.
+/// Returns `false` if there is no such
+fn remove_queued(id: u32, reason: Reason, iface: Rc<IFaceRef>, runtime_data: &RuntimeData) -> bool {
+    overflow::remove(id, reason, runtime_data)
+        || fullscreen::remove(id, reason, iface.clone(), runtime_data)
+        || dnd::remove(id, reason, iface, runtime_data)
+}
+
+/// Shows notification unless do-not-disturb, fullscreen or popups limit holds it back
+fn show_notification(
+    details: Details,
+    application: gtk::Application,
+    iface: Rc<IFaceRef>,
+    runtime_data: RuntimeData,
+) {
+    if dnd::suppresses(&details, &runtime_data) {
+        dnd::queue(details, &runtime_data);
+    } else if fullscreen::suppresses(&details, &runtime_data) {
+        fullscreen::queue(details, &runtime_data);
+    } else if overflow::should_queue(&details, &runtime_data) {
+        overflow::queue(details, &runtime_data);
+    } else {
+        new_notification(details, application, iface, runtime_data);
+    }
+}
+
 // FIXME too much windows breaks system
 fn new_notification(
     details: Details,
@@ -157,6 +416,11 @@ fn new_notification(
         #[strong]
         runtime_data,
         move |_window| {
+            // card is moved out of own window, it is not closed actually
+            if window.is_embedded() {
+                return;
+            }
+
             let reason = unsafe {
                 window
                     .inner
@@ -177,25 +441,29 @@ fn new_notification(
         }
     ));
 
-    window.inner.present();
+    // popup height changes while it is animated
+    window.widget().connect_child_revealed_notify(clone!(
+        #[strong]
+        runtime_data,
+        move |_| margins_update(runtime_data.clone())
+    ));
+
+    let stack = runtime_data.borrow().stack.clone();
+    if window.place_at_position() {
+        // pointed popup is out of the stack
+        window.inner.present();
+        margins_update_on_map(&window.inner, runtime_data.clone());
+    } else if group::place(&window, &application, iface, runtime_data.clone()) {
+        margins_update(runtime_data.clone());
+    } else if let Some(stack) = stack {
+        window.attach(&stack);
+    } else {
+        window.inner.present();
+        margins_update_on_map(&window.inner, runtime_data.clone());
+    }
 
-    glib::timeout_add_local(
-        Duration::from_millis(50),
-        clone!(
-            #[strong]
-            window,
-            #[strong]
-            runtime_data,
-            move || {
-                if window.inner.is_mapped() {
-                    margins_update(runtime_data.clone());
-                    glib::ControlFlow::Break
-                } else {
-                    glib::ControlFlow::Continue
-                }
-            }
-        ),
-    );
+    layer_update(&runtime_data);
 
     window.start_timeout();
+    center::refresh(&runtime_data);
 }
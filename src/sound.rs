@@ -0,0 +1,324 @@
+use std::path::{Path, PathBuf};
+
+use gtk::{glib, prelude::*};
+#[allow(unused_imports)]
+use log::*;
+
+use crate::{
+    config::{
+        sound::{Backend, Sound},
+        Config, CONFIG,
+    },
+    dbus::{Details, Hints},
+    dnd,
+    types::RuntimeData,
+};
+
+/// Plays sound for new notification unless it is muted
+pub fn play(details: &Details, runtime_data: &RuntimeData) {
+    let config = CONFIG.lock().unwrap().clone();
+    let dnd = dnd::suppresses(details, runtime_data);
+    if is_muted(&details.hints, &config.sound, dnd) {
+        debug!("Sound is muted for notification with id: {}", details.id);
+        return;
+    }
+
+    let Some(path) = resolve(&details.hints, &config, &data_dirs()) else {
+        return;
+    };
+
+    match config.sound.backend {
+        Backend::Null => info!(
+            "Sound for notification with id: {} is not played: {:?}",
+            details.id, path
+        ),
+        Backend::Media => {
+            debug!(
+                "Playing sound for notification with id: {}: {:?}",
+                details.id, path
+            );
+            let media = gtk::MediaFile::for_filename(&path);
+            // kept alive until playback is finished
+            runtime_data.borrow_mut().sounds.push(media.clone());
+            let remove = glib::clone!(
+                #[weak]
+                runtime_data,
+                move |media: &gtk::MediaFile| {
+                    runtime_data.borrow_mut().sounds.retain(|m| m != media);
+                }
+            );
+            media.connect_ended_notify(remove.clone());
+            media.connect_error_notify(move |media| {
+                if let Some(e) = media.error() {
+                    warn!("Failed to play sound: {}", e);
+                }
+                remove(media);
+            });
+            media.play();
+        }
+    }
+}
+
+/// `suppress-sound` hint, global mute and do-not-disturb silence notification
+fn is_muted(hints: &Hints, sound: &Sound, dnd: bool) -> bool {
+    hints.suppress_sound || sound.mute || dnd
+}
+
+/// Sound dirs by priority
+fn data_dirs() -> Vec<PathBuf> {
+    [glib::user_data_dir()]
+        .into_iter()
+        .chain(glib::system_data_dirs())
+        .map(|dir| dir.join("sounds"))
+        .collect()
+}
+
+/// Hints take precedence over category and urgency defaults
+fn resolve(hints: &Hints, config: &Config, data_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let theme = config.sound.theme.as_str();
+
+    if let Some(file) = &hints.sound_file {
+        // spec says it is a path, but clients send URIs as well
+        let path = if file.starts_with("file://") {
+            glib::filename_from_uri(file)
+                .inspect_err(|e| warn!("Invalid sound file URI {}: {}", file, e))
+                .ok()?
+                .0
+        } else {
+            PathBuf::from(file)
+        };
+        return Some(path).filter(|p| p.is_file());
+    }
+    if let Some(path) = hints
+        .sound_name
+        .as_deref()
+        .and_then(|n| lookup(n, theme, data_dirs))
+    {
+        return Some(path);
+    }
+
+    config
+        .category_defaults(hints.category.as_ref())
+        .sound
+        .or_else(|| config.sound.urgency.get(&hints.urgency).cloned())
+        .and_then(|sound| {
+            if Path::new(&sound).is_absolute() {
+                Some(PathBuf::from(sound)).filter(|p| p.is_file())
+            } else {
+                lookup(&sound, theme, data_dirs)
+            }
+        })
+}
+
+/// Looks up sound by name as freedesktop sound theme spec describes.
+/// Name is shortened by its last dash-separated part until something is found
+/// (`message-new-email` -> `message-new` -> `message`)
+fn lookup(name: &str, theme: &str, data_dirs: &[PathBuf]) -> Option<PathBuf> {
+    let themes = themes_chain(theme, data_dirs);
+
+    let mut shortened = name;
+    loop {
+        for theme in themes.iter() {
+            for dir in data_dirs.iter() {
+                for subdir in ["stereo", ""] {
+                    for ext in ["oga", "ogg", "wav"] {
+                        let path = dir
+                            .join(theme)
+                            .join(subdir)
+                            .join(format!("{}.{}", shortened, ext));
+                        if path.is_file() {
+                            return Some(path);
+                        }
+                    }
+                }
+            }
+        }
+
+        match shortened.rsplit_once('-') {
+            Some((shorter, _)) => shortened = shorter,
+            None => break,
+        }
+    }
+
+    warn!("Sound {} is not found in theme {}", name, theme);
+    None
+}
+
+/// Theme followed by ones it inherits from. `freedesktop` is always the last
+fn themes_chain(theme: &str, data_dirs: &[PathBuf]) -> Vec<String> {
+    let mut themes = vec![theme.to_owned()];
+    let mut index = 0;
+    while index < themes.len() {
+        let inherits = data_dirs
+            .iter()
+            .map(|dir| dir.join(&themes[index]).join("index.theme"))
+            .find_map(|path| {
+                let key_file = glib::KeyFile::new();
+                key_file
+                    .load_from_file(path, glib::KeyFileFlags::NONE)
+                    .ok()?;
+                key_file.string("Sound Theme", "Inherits").ok()
+            });
+        for parent in inherits.iter().flat_map(|i| i.split(',')) {
+            let parent = parent.trim();
+            if !parent.is_empty() && !themes.iter().any(|t| t == parent) {
+                themes.push(parent.to_owned());
+            }
+        }
+        index += 1;
+    }
+
+    if !themes.iter().any(|t| t == "freedesktop") {
+        themes.push("freedesktop".to_owned());
+    }
+    themes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        config::category::CategoryDefaults,
+        dbus::{Category, Urgency},
+    };
+
+    /// Creates empty file with parent dirs
+    fn touch(dir: &Path, path: &str) -> PathBuf {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "").unwrap();
+        path
+    }
+
+    fn sounds_dir() -> (TempDir, Vec<PathBuf>) {
+        let dir = TempDir::new().unwrap();
+        let data_dirs = vec![dir.path().to_owned()];
+        (dir, data_dirs)
+    }
+
+    #[test]
+    fn muted_by_hint_config_and_dnd() {
+        let sound = Sound::default();
+        let hints = Hints::default();
+        assert!(!is_muted(&hints, &sound, false));
+        assert!(is_muted(&hints, &sound, true));
+
+        let suppressed = Hints {
+            suppress_sound: true,
+            ..Default::default()
+        };
+        assert!(is_muted(&suppressed, &sound, false));
+
+        let mute = Sound {
+            mute: true,
+            ..Default::default()
+        };
+        assert!(is_muted(&hints, &mute, false));
+    }
+
+    #[test]
+    fn lookup_prefers_stereo_and_shortens_name() {
+        let (dir, data_dirs) = sounds_dir();
+        let mono = touch(dir.path(), "freedesktop/message.oga");
+        assert_eq!(
+            lookup("message-new-email", "freedesktop", &data_dirs),
+            Some(mono)
+        );
+
+        let stereo = touch(dir.path(), "freedesktop/stereo/message-new.oga");
+        assert_eq!(
+            lookup("message-new-email", "freedesktop", &data_dirs),
+            Some(stereo)
+        );
+        assert_eq!(lookup("bell", "freedesktop", &data_dirs), None);
+    }
+
+    #[test]
+    fn lookup_follows_inherits_and_freedesktop_fallback() {
+        let (dir, data_dirs) = sounds_dir();
+        fs::create_dir_all(dir.path().join("child")).unwrap();
+        fs::write(
+            dir.path().join("child/index.theme"),
+            "[Sound Theme]\nName=Child\nInherits=parent\n",
+        )
+        .unwrap();
+        let parent = touch(dir.path(), "parent/stereo/bell.oga");
+        let fallback = touch(dir.path(), "freedesktop/stereo/complete.oga");
+
+        assert_eq!(
+            themes_chain("child", &data_dirs),
+            ["child", "parent", "freedesktop"]
+        );
+        assert_eq!(lookup("bell", "child", &data_dirs), Some(parent));
+        assert_eq!(lookup("complete", "child", &data_dirs), Some(fallback));
+    }
+
+    #[test]
+    fn sound_file_hint_accepts_path_and_uri() {
+        let (dir, data_dirs) = sounds_dir();
+        let file = touch(dir.path(), "with space.oga");
+        let config = Config::default();
+
+        let hints = Hints {
+            sound_file: Some(file.to_str().unwrap().to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(resolve(&hints, &config, &data_dirs), Some(file.clone()));
+
+        let uri = glib::filename_to_uri(&file, None).unwrap();
+        assert!(uri.contains("%20"));
+        let hints = Hints {
+            sound_file: Some(uri.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(resolve(&hints, &config, &data_dirs), Some(file));
+
+        let hints = Hints {
+            sound_file: Some("/nonexistent/sound.oga".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(resolve(&hints, &config, &data_dirs), None);
+    }
+
+    #[test]
+    fn hint_then_category_then_urgency() {
+        let (dir, data_dirs) = sounds_dir();
+        let hinted = touch(dir.path(), "freedesktop/bell.oga");
+        let category = touch(dir.path(), "category.oga");
+        let urgency = touch(dir.path(), "freedesktop/dialog-warning.oga");
+
+        let mut config = Config::default();
+        config.categories.insert(
+            "email".to_owned(),
+            CategoryDefaults {
+                sound: Some(category.to_str().unwrap().to_owned()),
+                ..Default::default()
+            },
+        );
+        config
+            .sound
+            .urgency
+            .insert(Urgency::Critical, "dialog-warning".to_owned());
+
+        let mut hints = Hints {
+            sound_name: Some("bell".to_owned()),
+            category: Some(Category::from("email.arrived")),
+            urgency: Urgency::Critical,
+            ..Default::default()
+        };
+        assert_eq!(resolve(&hints, &config, &data_dirs), Some(hinted));
+
+        hints.sound_name = None;
+        assert_eq!(resolve(&hints, &config, &data_dirs), Some(category));
+
+        hints.category = None;
+        assert_eq!(resolve(&hints, &config, &data_dirs), Some(urgency));
+
+        hints.urgency = Urgency::Normal;
+        assert_eq!(resolve(&hints, &config, &data_dirs), None);
+    }
+}
@@ -0,0 +1,52 @@
+use std::{fs, io, path::PathBuf};
+
+use gtk::glib;
+#[allow(unused_imports)]
+use log::*;
+use serde::{Deserialize, Serialize};
+
+/// Daemon state that can survive restarts
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub dnd: bool,
+}
+
+impl State {
+    fn path() -> PathBuf {
+        glib::user_data_dir().join("rustyfications/state.ron")
+    }
+
+    pub fn load() -> Self {
+        let path = Self::path();
+
+        match fs::read_to_string(&path) {
+            Ok(s) => ron::from_str(&s).unwrap_or_else(|e| {
+                error!("Failed to parse state file {:?}: {}", path, e);
+                Self::default()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                error!("Failed to read state file {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let path = Self::path();
+
+        let result = ron::to_string(self)
+            .map_err(io::Error::other)
+            .and_then(|s| {
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(&path, s)
+            });
+
+        if let Err(e) = result {
+            error!("Failed to save state file {:?}: {}", path, e);
+        }
+    }
+}
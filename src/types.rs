@@ -1,10 +1,50 @@
-use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    rc::Rc,
+};
 
-use crate::gui::window::Window;
+use gtk::{gio, MediaFile};
+
+use crate::{
+    dbus::Details,
+    gui::{center::Center, group::Group, overflow::Overflow, stack::Stack, window::Window},
+    history::History,
+    utils::UserStyle,
+};
 
 pub type RuntimeData = Rc<RefCell<_RuntimeData>>;
 
 #[derive(Default)]
 pub struct _RuntimeData {
     pub windows: BTreeMap<u32, Window>,
+    /// Grouped windows by group key
+    pub groups: HashMap<String, Group>,
+    pub history: History,
+    pub center: Option<Center>,
+    pub overflow: Option<Overflow>,
+    /// Single surface for all popups. Not set if every popup has its own window
+    pub stack: Option<Stack>,
+    /// Do-not-disturb. Popups are not shown
+    pub dnd: bool,
+    /// Scheduled do-not-disturb
+    pub quiet_hours: bool,
+    /// Notifications arrived during do-not-disturb or quiet hours
+    pub dnd_queue: Vec<Details>,
+    /// Notifications waiting for free space on screen
+    pub overflow_queue: Vec<Details>,
+    /// Outputs with fullscreen client, reported over control interface.
+    /// Empty name is for unknown output
+    pub fullscreen_outputs: HashSet<String>,
+    /// Surfaces unmapped by `Hide` fullscreen policy
+    pub fullscreen_hidden: Vec<gtk::Window>,
+    /// Notifications waiting until fullscreen ends
+    pub fullscreen_queue: Vec<Details>,
+    /// Kept alive to hot-reload config
+    pub config_monitors: Vec<gio::FileMonitor>,
+    pub user_style: Option<UserStyle>,
+    /// Kept alive to follow color scheme changes
+    pub settings_portal: Option<gio::DBusProxy>,
+    /// Sounds that are being played
+    pub sounds: Vec<MediaFile>,
 }
@@ -1,63 +1,251 @@
-use std::{error::Error, rc::Rc};
+use std::{cell::RefCell, error::Error, path::Path, rc::Rc, time::Duration};
 
-pub use css::setup_styling;
+pub use css::{setup_styling, setup_user_styling, UserStyle};
+use gtk::{gio, glib, prelude::*};
 #[allow(unused_imports)]
 use log::*;
 
 use crate::{
     config::CONFIG,
-    dbus::{IFace, IFaceRef, Reason},
+    dbus::{Details, IFace, IFaceRef, Reason},
+    gui::{center, overflow, utils::layer_update},
+    history::Entry,
+    hooks::{self, Event},
     margins_update,
     types::RuntimeData,
 };
 
 mod css {
-    use std::collections::HashMap;
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    };
 
-    use gtk::{gdk, CssProvider};
-    use log::{debug, info};
+    use gtk::{
+        gdk, gio,
+        glib::{self, clone},
+        prelude::*,
+        CssProvider,
+    };
+    use log::{debug, error, info, warn};
 
-    pub fn setup_styling() {
-        let settings = gtk::Settings::default().unwrap();
-        settings.connect_gtk_theme_name_notify(|_| load_css());
-        // FIXME this doesn't catch theme variant changing
-        // settings.connect_gtk_application_prefer_dark_theme_notify(|_| load_css());
-        load_css();
+    use crate::{config::CONFIG, types::RuntimeData};
+
+    use super::watch_file;
+
+    /// Stylesheet from user config dir. Applied above the built-in one
+    pub struct UserStyle {
+        provider: CssProvider,
+        path: PathBuf,
+        _monitor: Option<gtk::gio::FileMonitor>,
     }
 
-    pub fn load_css() {
+    /// Built-in styles follow theme and its variant.
+    /// Single provider is reloaded in place on every change
+    pub fn setup_styling(runtime_data: &RuntimeData) {
         let provider = CssProvider::new();
+        gtk::style_context_add_provider_for_display(
+            &gdk::Display::default().expect("Could not connect to a display."),
+            &provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+
+        let settings = gtk::Settings::default().unwrap();
+        settings.connect_gtk_theme_name_notify(clone!(
+            #[strong]
+            provider,
+            move |_| load_css(&provider)
+        ));
+        settings.connect_gtk_application_prefer_dark_theme_notify(clone!(
+            #[strong]
+            provider,
+            move |_| load_css(&provider)
+        ));
+        load_css(&provider);
+
+        watch_color_scheme(runtime_data.clone());
+    }
 
+    /// Follows `org.freedesktop.appearance color-scheme` from settings portal
+    /// by switching `gtk-application-prefer-dark-theme`.
+    /// Without portal only GTK setting itself is tracked
+    fn watch_color_scheme(runtime_data: RuntimeData) {
+        glib::spawn_future_local(async move {
+            let proxy = match gio::DBusProxy::for_bus_future(
+                gio::BusType::Session,
+                gio::DBusProxyFlags::DO_NOT_LOAD_PROPERTIES,
+                None,
+                "org.freedesktop.portal.Desktop",
+                "/org/freedesktop/portal/desktop",
+                "org.freedesktop.portal.Settings",
+            )
+            .await
+            {
+                Ok(proxy) => proxy,
+                Err(e) => {
+                    warn!("Settings portal is not available: {}", e);
+                    return;
+                }
+            };
+
+            proxy.connect_g_signal(|_, _, signal, parameters| {
+                if signal != "SettingChanged" {
+                    return;
+                }
+                let namespace = parameters.child_value(0);
+                let key = parameters.child_value(1);
+                if namespace.str() == Some(APPEARANCE_NAMESPACE)
+                    && key.str() == Some(COLOR_SCHEME_KEY)
+                {
+                    if let Some(value) = color_scheme(&parameters.child_value(2)) {
+                        apply_color_scheme(value);
+                    }
+                }
+            });
+
+            let key = (APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY).to_variant();
+            // `ReadOne` is missing in older portals
+            let value = match proxy
+                .call_future("ReadOne", Some(&key), gio::DBusCallFlags::NONE, -1)
+                .await
+            {
+                Ok(value) => Ok(value),
+                Err(_) => {
+                    proxy
+                        .call_future("Read", Some(&key), gio::DBusCallFlags::NONE, -1)
+                        .await
+                }
+            };
+            match value.map(|value| color_scheme(&value)) {
+                Ok(Some(value)) => apply_color_scheme(value),
+                Ok(None) => warn!("Unexpected color scheme value from settings portal"),
+                Err(e) => warn!("Failed to read color scheme from settings portal: {}", e),
+            }
+
+            runtime_data.borrow_mut().settings_portal = Some(proxy);
+        });
+    }
+
+    const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+    const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+    /// Unwraps value that can be nested into variants and tuples
+    fn color_scheme(value: &glib::Variant) -> Option<u32> {
+        value.get::<u32>().or_else(|| {
+            (value.is_container() && value.n_children() == 1)
+                .then(|| value.child_value(0))
+                .and_then(|value| color_scheme(&value))
+        })
+    }
+
+    fn apply_color_scheme(value: u32) {
+        debug!("Color scheme from settings portal: {}", value);
+        let settings = gtk::Settings::default().unwrap();
+        match value {
+            // prefer dark
+            1 => settings.set_gtk_application_prefer_dark_theme(true),
+            // prefer light
+            2 => settings.set_gtk_application_prefer_dark_theme(false),
+            // no preference. Keep what GTK settings say
+            _ => {}
+        }
+    }
+
+    fn load_css(provider: &CssProvider) {
         let text = css_glob_export_string();
         let theme_colors = css_glob_export_colors(&text);
 
         let borders = theme_colors.get("borders").unwrap_or(&"gray");
         let theme_base_color = theme_colors.get("theme_base_color").unwrap_or(&"gray");
+        let theme_bg_color = theme_colors.get("theme_bg_color").unwrap_or(&"white");
+        let error_color = theme_colors.get("error_color").unwrap_or(&"red");
 
         info!(
-            "Loading CSS with border color: {} and base color: {}",
-            borders, theme_base_color
+            "Loading CSS with border color: {}, base color: {} and error color: {}",
+            borders, theme_base_color, error_color
         );
 
         provider.load_from_data(&format!(
             "
-    #notification {{
+    window.popup {{
+      background: none;
+    }}
+
+    #notification, #group {{
+      background-color: {theme_bg_color};
       border: 1pt solid {borders};
       border-radius: 5pt;
     }}
     
-    #notification.hover {{
+    #notification.hover, #group.hover {{
       background-color: {theme_base_color};
+    }}
+
+    #notification.urgency-critical {{
+      border-color: {error_color};
     }}",
         ));
 
+        info!("CSS loaded and applied successfully.");
+    }
+
+    /// Loads user stylesheet and reloads it on every change.
+    /// Does nothing if configured path is already in use
+    pub fn setup_user_styling(runtime_data: &RuntimeData) {
+        let path = CONFIG.lock().unwrap().style_path();
+        if runtime_data
+            .borrow()
+            .user_style
+            .as_ref()
+            .is_some_and(|style| style.path == path)
+        {
+            return;
+        }
+
+        let display = gdk::Display::default().expect("Could not connect to a display.");
+        if let Some(old) = runtime_data.borrow_mut().user_style.take() {
+            gtk::style_context_remove_provider_for_display(&display, &old.provider);
+        }
+
+        let provider = CssProvider::new();
+        provider.connect_parsing_error(|_, section, error| {
+            warn!("Failed to parse user stylesheet at {}: {}", section, error);
+        });
         gtk::style_context_add_provider_for_display(
-            &gdk::Display::default().expect("Could not connect to a display."),
+            &display,
             &provider,
-            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            gtk::STYLE_PROVIDER_PRIORITY_USER,
         );
+        load_user_css(&provider, &path);
 
-        info!("CSS loaded and applied successfully.");
+        let monitor = watch_file(
+            &path,
+            clone!(
+                #[strong]
+                provider,
+                #[strong]
+                path,
+                move || load_user_css(&provider, &path)
+            ),
+        )
+        .inspect_err(|e| error!("Failed to watch user stylesheet {:?}: {}", path, e))
+        .ok();
+
+        runtime_data.borrow_mut().user_style = Some(UserStyle {
+            provider,
+            path,
+            _monitor: monitor,
+        });
+    }
+
+    fn load_user_css(provider: &CssProvider, path: &Path) {
+        if path.exists() {
+            info!("Loading user stylesheet: {:?}", path);
+            provider.load_from_path(path);
+        } else {
+            debug!("User stylesheet {:?} doesn't exist", path);
+            provider.load_from_data("");
+        }
     }
 
     pub fn css_glob_export_string() -> String {
@@ -108,12 +296,89 @@ pub async fn close_hook(
         ),
     }
 
-    runtime_data.borrow_mut().windows.remove(&id);
+    let window = runtime_data.borrow_mut().windows.remove(&id);
+    if let Some(window) = &window {
+        hooks::run(Event::Close(died_from), &window.details());
+    }
+    if let Some(details) = window
+        .filter(|w| !w.skips_history())
+        .map(|w| w.details())
+        .filter(|d| !d.hints.transient)
+    {
+        runtime_data
+            .borrow_mut()
+            .history
+            .push(Entry::new(&details, Some(died_from)));
+    }
+    overflow::promote(&runtime_data);
+    layer_update(&runtime_data);
     margins_update(runtime_data.clone());
+    center::refresh(&runtime_data);
 
     debug!("Margins updated after closing notification with ID: {}", id);
 }
 
+/// Same as `close_hook` but for notification that was queued and never shown
+pub fn close_queued(
+    details: &Details,
+    reason: Reason,
+    iface: Rc<IFaceRef>,
+    runtime_data: &RuntimeData,
+) {
+    hooks::run(Event::Close(reason), details);
+    if !details.hints.transient {
+        runtime_data
+            .borrow_mut()
+            .history
+            .push(Entry::new(details, Some(reason)));
+    }
+
+    let id = details.id;
+    glib::spawn_future_local(async move {
+        if let Err(e) = IFace::notification_closed(iface.signal_context(), id, reason).await {
+            error!(
+                "Error while closing queued notification for ID: {}, Error: {:?}",
+                id, e
+            );
+        }
+    });
+}
+
+/// Calls `f` every time file is changed, created or removed.
+/// Bursts of events (e.g. editor saving file) are coalesced into one call
+pub fn watch_file(path: &Path, f: impl Fn() + 'static) -> Result<gio::FileMonitor, glib::Error> {
+    let monitor = gio::File::for_path(path)
+        .monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)?;
+
+    let f = Rc::new(f);
+    let pending: Rc<RefCell<Option<glib::SourceId>>> = Default::default();
+    monitor.connect_changed(move |_, _, _, event| {
+        use gio::FileMonitorEvent as E;
+        if !matches!(
+            event,
+            E::ChangesDoneHint | E::Created | E::Deleted | E::Renamed | E::MovedIn | E::MovedOut
+        ) {
+            return;
+        }
+        debug!("File event: {:?}", event);
+
+        if let Some(source) = pending.borrow_mut().take() {
+            source.remove();
+        }
+        let f = f.clone();
+        let pending_clone = pending.clone();
+        pending.borrow_mut().replace(glib::timeout_add_local_once(
+            Duration::from_millis(200),
+            move || {
+                pending_clone.borrow_mut().take();
+                f();
+            },
+        ));
+    });
+
+    Ok(monitor)
+}
+
 pub fn logger_init() -> Result<(), Box<dyn Error>> {
     use sys_logger::{connected_to_journal, JournalLog};
 